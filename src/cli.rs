@@ -0,0 +1,301 @@
+use std::time::Instant;
+
+use anders_kluring::solver::{
+    BoardMask, BoxSize, Layout, Placement, RandomRestarts, RenderOptions, RestartLimit, ScoreWeights, ShapeBag, Solver, StrategyKind, StrategySettings,
+};
+
+const USAGE: &str = "usage: anders-kluring solve [--n <count>] [--crunch <max attempts>] \
+[--strategy greedy|beam|exact] [--beam <width>] [--max-area <area>] [--exact] \
+[--weights <term=weight,...>] [--weight <term=weight>] [--pieces <file>] \
+[--counts <count,count,...>] [--seed <seed>] [--restarts <runs>|<seconds>s] [--save <layout.json|.ron>] [--load <layout.json|.ron>] [--ascii] \
+[--png <file>] [--svg <file>] [--cell <pixels>] [--grid] [--no-outlines] \
+[--lock <layout.json|.ron>] [--lock-first <count>] [--box <width>x<height>] [--mask <file>]";
+
+pub struct SolveArgs {
+    pub n: u16,
+    pub crunch: usize,
+    pub strategy: StrategyKind,
+    pub settings: StrategySettings,
+    pub weights: ScoreWeights,
+    pub pieces: Option<String>,
+    pub counts: Vec<Option<u16>>,
+    /// Random if not given.
+    pub seed: Option<u64>,
+    /// Solve repeatedly with consecutive seeds and keep the smallest board.
+    pub restarts: Option<RestartLimit>,
+    /// Layout file to write the finished board to.
+    pub save: Option<String>,
+    /// Layout file to show instead of solving.
+    pub load: Option<String>,
+    /// Print the board as a character grid after the summary.
+    pub ascii: bool,
+    /// Layout whose placements the solve has to build around.
+    pub lock: Option<String>,
+    /// Lock only this many of its first placements.
+    pub lock_first: Option<usize>,
+    /// Pack into a fixed box instead of the smallest rectangle.
+    pub container: Option<BoxSize>,
+    /// Grid of `#` and `.` whose open cells are packed into instead.
+    pub mask: Option<String>,
+    /// Image files to render the finished board to.
+    pub png: Option<String>,
+    pub svg: Option<String>,
+    pub render: RenderOptions,
+}
+
+impl Default for SolveArgs {
+    fn default() -> Self {
+        SolveArgs {
+            n: 1,
+            crunch: 0,
+            strategy: StrategyKind::Greedy,
+            settings: StrategySettings::default(),
+            weights: ScoreWeights::default(),
+            pieces: None,
+            counts: Vec::new(),
+            seed: None,
+            restarts: None,
+            save: None,
+            load: None,
+            ascii: false,
+            lock: None,
+            lock_first: None,
+            container: None,
+            mask: None,
+            png: None,
+            svg: None,
+            render: RenderOptions::default(),
+        }
+    }
+}
+
+/// Whether the arguments ask for a batch run instead of the window.
+pub fn is_headless(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "solve" || arg == "--headless")
+}
+
+fn parse_value<T>(flag: &str, value: Option<&String>) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = value.ok_or_else(|| format!("missing value for {}", flag))?;
+    value.parse::<T>().map_err(|err| format!("invalid value for {}: {} ({})", flag, value, err))
+}
+
+impl SolveArgs {
+    pub fn parse(args: &[String]) -> Result<SolveArgs, String> {
+        let mut solve_args = SolveArgs::default();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "solve" | "--headless" => {},
+                "--n" => solve_args.n = parse_value(arg, iter.next())?,
+                "--crunch" => solve_args.crunch = parse_value(arg, iter.next())?,
+                "--strategy" => solve_args.strategy = parse_value(arg, iter.next())?,
+                "--beam" => solve_args.settings.beam_width = parse_value(arg, iter.next())?,
+                "--max-area" => solve_args.settings.max_area = parse_value(arg, iter.next())?,
+                "--exact" => solve_args.strategy = StrategyKind::Exact,
+                "--pieces" => solve_args.pieces = Some(parse_value(arg, iter.next())?),
+                "--counts" => {
+                    let counts = iter.next().ok_or_else(|| format!("missing value for {}", arg))?;
+                    solve_args.counts = ShapeBag::parse_counts(counts)?;
+                },
+                "--seed" => solve_args.seed = Some(parse_value(arg, iter.next())?),
+                "--restarts" => solve_args.restarts = Some(parse_value(arg, iter.next())?),
+                "--save" => solve_args.save = Some(parse_value(arg, iter.next())?),
+                "--load" => solve_args.load = Some(parse_value(arg, iter.next())?),
+                "--ascii" => solve_args.ascii = true,
+                "--lock" => solve_args.lock = Some(parse_value(arg, iter.next())?),
+                "--lock-first" => solve_args.lock_first = Some(parse_value(arg, iter.next())?),
+                "--box" => solve_args.container = Some(parse_value(arg, iter.next())?),
+                "--mask" => solve_args.mask = Some(parse_value(arg, iter.next())?),
+                "--png" => solve_args.png = Some(parse_value(arg, iter.next())?),
+                "--svg" => solve_args.svg = Some(parse_value(arg, iter.next())?),
+                "--cell" => solve_args.render.cell_size = parse_value(arg, iter.next())?,
+                "--grid" => solve_args.render.grid_lines = true,
+                "--no-outlines" => solve_args.render.outlines = false,
+                "--weights" => solve_args.weights = parse_value(arg, iter.next())?,
+                "--weight" => {
+                    let assignment = iter.next().ok_or_else(|| format!("missing value for {}", arg))?;
+                    solve_args.weights.set(assignment)?;
+                },
+                "--help" | "-h" => return Err(USAGE.into()),
+                _ => return Err(format!("unknown argument: {}\n{}", arg, USAGE)),
+            }
+        }
+
+        Ok(solve_args)
+    }
+}
+
+/// Solves once, or repeatedly if restarts were asked for, and returns the best
+/// solver with the number of runs.
+fn solve(solve_args: &SolveArgs, start: Instant) -> Result<(Solver, usize), String> {
+    let mut bag = match &solve_args.pieces {
+        Some(path) => ShapeBag::from_file(path, solve_args.n)?,
+        None => ShapeBag::load(solve_args.n),
+    };
+    bag.set_counts(&solve_args.counts)?;
+
+    let lock_layout = solve_args.lock.as_ref().map(Layout::load).transpose()?;
+    let locked: Vec<Placement> = match &lock_layout {
+        Some(layout) => layout
+            .placements
+            .iter()
+            .take(solve_args.lock_first.unwrap_or(usize::MAX))
+            .map(Placement::from)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mask = match &solve_args.mask {
+        Some(_) if solve_args.container.is_some() => return Err("--box and --mask can't be combined".into()),
+        Some(path) => Some(BoardMask::from_file(path)?),
+        None => None,
+    };
+
+    // locked placements keep the box or mask they were made in, unless told otherwise
+    let (container, mask) = match &lock_layout {
+        Some(layout) if solve_args.container.is_none() && mask.is_none() => match layout.mask() {
+            Some(layout_mask) => (None, Some(layout_mask)),
+            None => (layout.container, None),
+        },
+        _ => (solve_args.container, mask),
+    };
+
+    let empty_board = || {
+        let solver = Solver::new(bag.clone(), solve_args.crunch);
+        match (container, &mask) {
+            (Some(size), _) => solver.with_container(size),
+            (None, Some(mask)) => solver.with_mask(mask),
+            (None, None) => solver,
+        }
+    };
+
+    // check once, so every run below can take the locked placements for granted
+    empty_board().with_locked(&locked)?;
+
+    let build_solver = |seed| {
+        let mut solver = empty_board()
+            .with_strategy(solve_args.strategy.build(&solve_args.settings))
+            .with_seed(seed)
+            .with_locked(&locked)
+            .expect("Locked placements were checked.");
+        solver.board.weights = solve_args.weights.clone();
+        solver
+    };
+
+    let seed = solve_args.seed.unwrap_or_else(rand::random);
+    let limit = solve_args.restarts.unwrap_or(RestartLimit::Runs(1));
+
+    let mut restarts = RandomRestarts::new(seed, build_solver);
+    while limit.allows(restarts.runs, start.elapsed()) {
+        let run_seed = restarts.next_seed();
+        let (area, _) = restarts.run();
+
+        if solve_args.restarts.is_some() {
+            println!(
+                "run={} seed={} area={} best={}",
+                restarts.runs,
+                run_seed,
+                area,
+                restarts.best_area().unwrap_or(area),
+            );
+        }
+    }
+
+    let runs = restarts.runs;
+    Ok((restarts.best.expect("At least one run."), runs))
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let solve_args = SolveArgs::parse(args)?;
+
+    let start = Instant::now();
+
+    // a loaded layout brings its own pieces, n, counts and strategy settings
+    let (solver, runs, n, pieces, counts, settings) = match &solve_args.load {
+        Some(path) => {
+            let layout = Layout::load(path)?;
+            let solver = layout.to_solver(layout.bag()?)?;
+            (solver, 0, layout.n, layout.pieces, layout.counts, layout.settings)
+        },
+        None => {
+            let (solver, runs) = solve(&solve_args, start)?;
+            (
+                solver,
+                runs,
+                solve_args.n,
+                solve_args.pieces.clone(),
+                solve_args.counts.clone(),
+                solve_args.settings.clone(),
+            )
+        },
+    };
+
+    let elapsed = start.elapsed();
+    let bounds = &solver.board.bounds;
+    let (width, height) = if bounds.is_default() {
+        (0, 0)
+    } else {
+        (bounds.width(), bounds.height())
+    };
+
+    // like whether the exact strategy proved its packing or left it to greedy
+    let report = solver.strategy.report().map(|report| format!(" {}", report)).unwrap_or_default();
+
+    println!(
+        "n={} crunch={} strategy={} seed={} runs={} width={} height={} area={} attempts={} time={:.3}s{}",
+        n,
+        solver.board.max_attempts,
+        solver.strategy.name(),
+        solver.board.seed,
+        runs,
+        width,
+        height,
+        bounds.area(),
+        solver.board.attempts,
+        elapsed.as_secs_f64(),
+        report,
+    );
+
+    // pieces left over only say the strategy gave up, not that the set can't fit
+    if let Some(container) = &solver.board.container {
+        let left = solver.bag.remaining_total();
+        let verdict = if left == 0 {
+            "fits".to_string()
+        } else {
+            format!("{} left {} pieces", solver.strategy.name(), left)
+        };
+        println!(
+            "{}={}x{} open={} placed={} left={} {}",
+            if solver.board.masked { "mask" } else { "box" },
+            container.width(),
+            container.height(),
+            container.area() - solver.drawn_obstacles().count() as i32,
+            solver.placements.len(),
+            left,
+            verdict,
+        );
+    }
+
+    if solve_args.ascii {
+        print!("{}", solver.to_ascii());
+    }
+
+    if let Some(path) = &solve_args.png {
+        solver.save_png(path, &solve_args.render)?;
+    }
+
+    if let Some(path) = &solve_args.svg {
+        solver.save_svg(path, &solve_args.render)?;
+    }
+
+    if let Some(path) = &solve_args.save {
+        Layout::new(&solver, n, pieces, counts, settings).save(path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! Export buttons and shortcuts for the shown board.
+
+use bevy::prelude::*;
+use kayak_ui::prelude::widgets::TextBoxState;
+
+use super::{ui::is_typing, SolverState};
+
+pub struct ExportAsciiEvent {}
+
+const ASCII_EXPORT_PATH: &str = "board.txt";
+
+/// Ctrl+E exports the board as text.
+pub fn export_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    text_boxes: Query<&TextBoxState>,
+    mut export_ascii: EventWriter<ExportAsciiEvent>,
+) {
+    if is_typing(&text_boxes) || !keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::E) {
+        export_ascii.send(ExportAsciiEvent {});
+    }
+}
+
+/// Prints the board as a character grid and writes it to `board.txt`.
+pub fn export_ascii(solver: Res<SolverState>) {
+    let ascii = solver.to_ascii();
+    print!("{}", ascii);
+
+    match std::fs::write(ASCII_EXPORT_PATH, &ascii) {
+        Ok(()) => println!("Wrote board to {}", ASCII_EXPORT_PATH),
+        Err(err) => println!("Could not write {}: {}", ASCII_EXPORT_PATH, err),
+    }
+}
@@ -0,0 +1,129 @@
+//! Hints: the solver's best next placements drawn over the board, so manual
+//! play gets some feedback.
+
+use anders_kluring::solver::shape_letter;
+use bevy::prelude::*;
+use kayak_ui::prelude::widgets::TextBoxState;
+
+use super::{tile::TILE_SIZE, ui::is_typing, SolverState};
+
+pub struct HintEvent {}
+
+/// Marks the sprites and labels of a shown hint.
+#[derive(Component)]
+pub struct HintGhost;
+
+const HINT_COUNT: usize = 3;
+
+/// Best hint first.
+const HINT_COLORS: [Color; HINT_COUNT] = [
+    Color::rgba(1., 0.85, 0.2, 0.55),
+    Color::rgba(0.3, 0.8, 1., 0.45),
+    Color::rgba(0.8, 0.5, 1., 0.35),
+];
+
+/// H asks for a hint.
+pub fn hint_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    text_boxes: Query<&TextBoxState>,
+    mut hint: EventWriter<HintEvent>,
+) {
+    if is_typing(&text_boxes) {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::H) && !keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        hint.send(HintEvent {});
+    }
+}
+
+/// Hints are only good for the board they were found on.
+pub fn clear_hints(
+    solver: Res<SolverState>,
+    hints: Query<Entity, With<HintGhost>>,
+    mut commands: Commands,
+) {
+    if !solver.is_changed() {
+        return;
+    }
+
+    for hint in hints.iter() {
+        commands.entity(hint).despawn();
+    }
+}
+
+pub fn show_hints(
+    solver: Res<SolverState>,
+    hints: Query<Entity, With<HintGhost>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    for hint in hints.iter() {
+        commands.entity(hint).despawn();
+    }
+
+    let candidates = solver.hints(HINT_COUNT);
+    if candidates.is_empty() {
+        println!("No placement left to hint at");
+        return;
+    }
+
+    let font = asset_server.load("font/roboto.ttf");
+
+    // worst first, so the best ends up on top where they overlap
+    for (rank, candidate) in candidates.iter().enumerate().rev() {
+        let placement = &candidate.placement;
+        let z = 5. - rank as f32 * 0.1;
+        println!(
+            "Hint {}: {} at {}, {} scores {}",
+            rank + 1,
+            shape_letter(placement.permutation.index),
+            placement.pos.x,
+            placement.pos.y,
+            candidate.score,
+        );
+
+        let tiles: Vec<Vec2> = solver.bag
+            .mask(&placement.permutation)
+            .tiles
+            .iter()
+            .map(|tile| {
+                let pos = *tile + placement.pos;
+                Vec2::new(pos.x as f32, pos.y as f32) * TILE_SIZE
+            })
+            .collect();
+
+        for tile in &tiles {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: HINT_COLORS[rank],
+                        custom_size: Some(Vec2::splat(TILE_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(tile.extend(z)),
+                    ..default()
+                },
+                HintGhost,
+            ));
+        }
+
+        let center = tiles.iter().sum::<Vec2>() / tiles.len() as f32;
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("#{} {}", rank + 1, candidate.score),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 12.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: Transform::from_translation(center.extend(z + 0.05)),
+                ..default()
+            },
+            HintGhost,
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+//! Undo, redo and the timeline. Every change redraws the board from the
+//! placements left, like loading a layout does.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use kayak_ui::prelude::widgets::TextBoxState;
+
+use super::{
+    clear_tiles, redraw_tiles, tile::ChunkManager, ui::is_typing, DrawObstaclesEvent, PlaceShapeEvent, SolverState,
+};
+
+pub enum HistoryEvent {
+    Undo,
+    Redo,
+    /// Show the board after this many placements.
+    Seek(usize),
+}
+
+/// Ctrl+Z undoes, Ctrl+Y or Ctrl+Shift+Z redoes.
+pub fn history_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    text_boxes: Query<&TextBoxState>,
+    mut history_events: EventWriter<HistoryEvent>,
+) {
+    if is_typing(&text_boxes) || !keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+
+    let shift = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+
+    if keys.just_pressed(KeyCode::Y) || (shift && keys.just_pressed(KeyCode::Z)) {
+        history_events.send(HistoryEvent::Redo);
+    } else if keys.just_pressed(KeyCode::Z) {
+        history_events.send(HistoryEvent::Undo);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_history(
+    mut history_events: EventReader<HistoryEvent>,
+    mut solver: ResMut<SolverState>,
+    mut place_shape_event: EventWriter<PlaceShapeEvent>,
+    mut draw_obstacles: EventWriter<DrawObstaclesEvent>,
+    mut commands: Commands,
+    chunk_manager: Res<ChunkManager>,
+    mut tilemap: Query<&mut TileStorage>,
+) {
+    // scrubbing sends plenty of events, only redraw for where they end up
+    let total = solver.history_len();
+    let mut step = solver.placements.len();
+    for event in history_events.iter() {
+        step = match event {
+            HistoryEvent::Undo => step.saturating_sub(1),
+            HistoryEvent::Redo => (step + 1).min(total),
+            HistoryEvent::Seek(target) => (*target).min(total),
+        };
+    }
+
+    if step == solver.placements.len() {
+        return;
+    }
+
+    clear_tiles(&solver, &mut commands, &chunk_manager, &mut tilemap);
+    solver.rewind_to(step);
+    redraw_tiles(&solver, &mut place_shape_event, &mut draw_obstacles);
+}
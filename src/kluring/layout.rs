@@ -0,0 +1,88 @@
+//! Save and load buttons for finished boards.
+
+use anders_kluring::solver::Layout;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use super::{
+    clear_tiles, manual::ManualPlay, redraw_tiles, task::SolveTask, tile::ChunkManager,
+    ui::InputFieldsState, DrawObstaclesEvent, PlaceShapeEvent, RunConfig, SolverState,
+};
+
+pub struct SaveLayoutEvent {}
+
+pub struct LoadLayoutEvent {}
+
+const DEFAULT_LAYOUT_PATH: &str = "layout.json";
+
+fn layout_path(input_fields: &Query<&InputFieldsState>) -> String {
+    input_fields
+        .iter()
+        .next()
+        .map(|input_field| input_field.layout.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| DEFAULT_LAYOUT_PATH.into())
+}
+
+pub fn save_layout(
+    solver: Res<SolverState>,
+    run_config: Res<RunConfig>,
+    input_fields: Query<&InputFieldsState>,
+) {
+    let path = layout_path(&input_fields);
+    let layout = Layout::new(
+        &solver,
+        run_config.n,
+        run_config.pieces.clone(),
+        run_config.counts.clone(),
+        run_config.settings.clone(),
+    );
+
+    match layout.save(&path) {
+        Ok(()) => println!("Saved {} placements to {}", layout.placements.len(), path),
+        Err(message) => println!("{}", message),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn load_layout(
+    mut solver: ResMut<SolverState>,
+    mut solve_task: ResMut<SolveTask>,
+    mut run_config: ResMut<RunConfig>,
+    mut manual: ResMut<ManualPlay>,
+    mut place_shape_event: EventWriter<PlaceShapeEvent>,
+    mut draw_obstacles: EventWriter<DrawObstaclesEvent>,
+    mut commands: Commands,
+    chunk_manager: Res<ChunkManager>,
+    mut tilemap: Query<&mut TileStorage>,
+    input_fields: Query<&InputFieldsState>,
+) {
+    let path = layout_path(&input_fields);
+
+    let loaded = Layout::load(&path).and_then(|layout| {
+        let solver = layout.to_solver(layout.bag()?)?;
+        Ok((layout, solver))
+    });
+
+    let (layout, loaded) = match loaded {
+        Ok(loaded) => loaded,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        },
+    };
+
+    solve_task.show_loaded(loaded.placements.len());
+    manual.active = false;
+    clear_tiles(&solver, &mut commands, &chunk_manager, &mut tilemap);
+
+    solver.0 = loaded;
+    redraw_tiles(&solver, &mut place_shape_event, &mut draw_obstacles);
+
+    *run_config = RunConfig {
+        n: layout.n,
+        pieces: layout.pieces,
+        counts: layout.counts,
+        settings: layout.settings,
+    };
+}
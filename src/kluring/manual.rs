@@ -0,0 +1,236 @@
+//! Manual play: pick pieces from the tray and place them yourself, to see
+//! whether you can beat the solver.
+
+use anders_kluring::solver::{GlobalPos, Permutation, Placement, ShapePermutation};
+use bevy::prelude::*;
+use kayak_ui::prelude::{widgets::TextBoxState, EventDispatcher};
+
+use super::{
+    task::{SolveStatus, SolveTask},
+    tile::TILE_SIZE,
+    ui::is_typing,
+    PlaceShapeEvent, SolverState,
+};
+
+#[derive(Resource)]
+pub struct ManualPlay {
+    pub active: bool,
+    /// Shape picked from the tray.
+    pub selected: Option<usize>,
+    pub permutation: Permutation,
+}
+
+impl Default for ManualPlay {
+    fn default() -> Self {
+        ManualPlay {
+            active: false,
+            selected: None,
+            permutation: Permutation::from_index(0),
+        }
+    }
+}
+
+impl ManualPlay {
+    /// The selected piece in its current orientation, placed at `pos`.
+    pub fn placement(&self, pos: GlobalPos) -> Option<Placement> {
+        Some(Placement {
+            permutation: ShapePermutation {
+                index: self.selected?,
+                permutation: self.permutation,
+            },
+            pos,
+        })
+    }
+}
+
+pub struct SelectPieceEvent(pub usize);
+
+/// Marks a sprite showing where the selected piece would go.
+#[derive(Component)]
+pub struct GhostTile;
+
+const GHOST_FITS: Color = Color::rgba(0.4, 1., 0.4, 0.6);
+const GHOST_OVERLAPS: Color = Color::rgba(1., 0.3, 0.3, 0.6);
+
+/// Tile under the cursor, if it's over the board rather than the panel.
+pub fn cursor_tile(
+    windows: &Query<&Window>,
+    cameras: &Query<(&Camera, &GlobalTransform)>,
+    dispatchers: &Query<&EventDispatcher>,
+) -> Option<GlobalPos> {
+    if dispatchers.iter().any(|dispatcher| dispatcher.contains_cursor()) {
+        return None;
+    }
+
+    let cursor = windows.iter().next()?.cursor_position()?;
+    let (camera, camera_transform) = cameras.iter().next()?;
+    let world = camera.viewport_to_world(camera_transform, cursor)?.origin;
+
+    // tiles are centered on multiples of the tile size
+    Some(GlobalPos {
+        x: (world.x / TILE_SIZE).round() as i32,
+        y: (world.y / TILE_SIZE).round() as i32,
+    })
+}
+
+/// R rotates, F flips, Tab or the number keys pick a piece, Escape puts it down.
+pub fn manual_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    text_boxes: Query<&TextBoxState>,
+    solver: Res<SolverState>,
+    mut manual: ResMut<ManualPlay>,
+    mut select_piece: EventWriter<SelectPieceEvent>,
+) {
+    if !manual.active || is_typing(&text_boxes) {
+        return;
+    }
+
+    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::R) {
+        manual.permutation.rotation = (manual.permutation.rotation + 1) % 4;
+    }
+
+    if keys.just_pressed(KeyCode::F) {
+        manual.permutation.flipped = !manual.permutation.flipped;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        manual.selected = None;
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        let shape_count = solver.bag.shape_count();
+        let start = manual.selected.map_or(0, |selected| selected + 1);
+        let next = (0..shape_count)
+            .map(|offset| (start + offset) % shape_count)
+            .find(|index| solver.bag.remaining(*index) > 0);
+        if let Some(next) = next {
+            select_piece.send(SelectPieceEvent(next));
+        }
+    }
+
+    const NUMBER_KEYS: [KeyCode; 9] = [
+        KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+        KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+        KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    ];
+    for (index, key) in NUMBER_KEYS.iter().enumerate() {
+        if keys.just_pressed(*key) && index < solver.bag.shape_count() {
+            select_piece.send(SelectPieceEvent(index));
+        }
+    }
+}
+
+pub fn select_piece(
+    mut select_piece_events: EventReader<SelectPieceEvent>,
+    solver: Res<SolverState>,
+    mut manual: ResMut<ManualPlay>,
+) {
+    for SelectPieceEvent(index) in select_piece_events.iter() {
+        if solver.bag.remaining(*index) > 0 {
+            manual.selected = Some(*index);
+        }
+    }
+}
+
+/// Redraws the ghost of the selected piece under the cursor, green where it fits.
+#[allow(clippy::too_many_arguments)]
+pub fn update_ghost(
+    manual: Res<ManualPlay>,
+    solver: Res<SolverState>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    dispatchers: Query<&EventDispatcher>,
+    ghosts: Query<Entity, With<GhostTile>>,
+    mut shown: Local<Option<(GlobalPos, usize, usize)>>,
+    mut commands: Commands,
+) {
+    let placement = cursor_tile(&windows, &cameras, &dispatchers)
+        .filter(|_| manual.active)
+        .and_then(|pos| manual.placement(pos))
+        .filter(|placement| solver.bag.remaining(placement.permutation.index) > 0);
+
+    let key = placement.map(|placement| {
+        (placement.pos, placement.permutation.index, placement.permutation.permutation.index())
+    });
+    if key == *shown && !solver.is_changed() {
+        return;
+    }
+    *shown = key;
+
+    for ghost in ghosts.iter() {
+        commands.entity(ghost).despawn();
+    }
+
+    let Some(placement) = placement else {
+        return;
+    };
+
+    let mask = solver.bag.mask(&placement.permutation);
+    let color = if solver.board.fits(mask, placement.pos) {
+        GHOST_FITS
+    } else {
+        GHOST_OVERLAPS
+    };
+
+    for tile in &mask.tiles {
+        let pos = *tile + placement.pos;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(TILE_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(pos.x as f32 * TILE_SIZE, pos.y as f32 * TILE_SIZE, 10.),
+                ..default()
+            },
+            GhostTile,
+        ));
+    }
+}
+
+/// Left click drops the selected piece where the ghost is, unless it overlaps.
+#[allow(clippy::too_many_arguments)]
+pub fn place_manually(
+    buttons: Res<Input<MouseButton>>,
+    mut manual: ResMut<ManualPlay>,
+    mut solver: ResMut<SolverState>,
+    mut solve_task: ResMut<SolveTask>,
+    mut place_shape_event: EventWriter<PlaceShapeEvent>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    dispatchers: Query<&EventDispatcher>,
+) {
+    if !manual.active || !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(placement) = cursor_tile(&windows, &cameras, &dispatchers)
+        .and_then(|pos| manual.placement(pos)) else {
+        return;
+    };
+
+    if let Err(message) = solver.try_place(&placement) {
+        println!("{}", message);
+        return;
+    }
+
+    place_shape_event.send(PlaceShapeEvent {
+        permutation: placement.permutation,
+        pos: placement.pos,
+    });
+
+    solve_task.placed = solver.placements.len();
+    if solver.is_done() {
+        solve_task.status = SolveStatus::Done;
+    }
+
+    // the last one of its kind is gone, pick up the next kind
+    if solver.bag.remaining(placement.permutation.index) == 0 {
+        manual.selected = solver.bag.iter_available().next().map(|shape| shape.index);
+    }
+}
@@ -1,17 +1,33 @@
 use std::collections::{HashSet, HashMap, hash_map::Entry};
 
+use anders_kluring::solver::{
+    BoardMask, BoxSize, GlobalPos, RestartLimit, ScoreWeights, ShapeBag, ShapePermutation, Solver, StrategyKind,
+    StrategySettings, iter_moore,
+};
 use bevy::{prelude::*, input::mouse::MouseWheel};
 use bevy_ecs_tilemap::prelude::*;
 
-use crate::kluring::{shape::Permutation, tile::{TILEMAP_SIZE, create_chunk}};
+use crate::kluring::tile::{TILEMAP_SIZE, create_chunk, to_chunk_pos, from_chunk_tile};
 
 use self::{
-    shape::{ShapeBag, ShapePermutation},
-    ui::{ShowUiPlugin, InputFieldsState}, 
-    tile::{TilePlugin, ChunkManager, BorderTile, CHUNK_SIZE, GlobalPos}
+    export::ExportAsciiEvent,
+    hint::HintEvent,
+    history::HistoryEvent,
+    manual::{ManualPlay, SelectPieceEvent},
+    layout::{LoadLayoutEvent, SaveLayoutEvent},
+    playback::{Playback, PlaybackEvent},
+    ui::{ShowUiPlugin, InputFieldsState},
+    tile::{TilePlugin, ChunkManager, BorderTile},
+    task::{SolveTask, SolveUpdate},
 };
 
-mod shape;
+mod export;
+mod hint;
+mod history;
+mod layout;
+mod manual;
+mod playback;
+mod task;
 mod ui;
 mod tile;
 
@@ -23,20 +39,29 @@ impl Plugin for KluringPlugin {
 
         app
             .add_event::<RestartEvent>()
+            .add_event::<CancelEvent>()
+            .add_event::<SaveLayoutEvent>()
+            .add_event::<LoadLayoutEvent>()
+            .add_event::<ExportAsciiEvent>()
+            .add_event::<PlaybackEvent>()
+            .add_event::<HistoryEvent>()
+            .add_event::<SelectPieceEvent>()
+            .add_event::<HintEvent>()
             .add_plugin(TilemapPlugin)
             .add_plugin(ShowUiPlugin)
             .add_plugin(TilePlugin)
             .add_event::<PlaceShapeEvent>()
-            .insert_resource(ShapeBag::load(1))
-            .insert_resource(BoardState { 
-                scored_positions: HashMap::new(),
-                bounds: Bounds::new(),
-                attempts: 0,
-                max_attempts: 0,
-            })
+            .add_event::<DrawObstaclesEvent>()
+            .insert_resource(SolverState(Solver::new(ShapeBag::load(1), 0)))
+            .init_resource::<SolveTask>()
+            .init_resource::<RunConfig>()
+            .init_resource::<Playback>()
+            .init_resource::<ManualPlay>()
+            .add_startup_system(start_solve)
             .add_systems(
                 (
-                    find_best_shape,
+                    playback::advance_playback,
+                    receive_placements,
                     place_shape,
                 )
                 .chain()
@@ -44,15 +69,31 @@ impl Plugin for KluringPlugin {
             )
             .add_system(
                 update_boundary_score,
-            )/*.run_if(on_event::<PlaceShapeEvent>())*/
-            //.add_system(.run_if(on_event::<RecalculateBoundary>()))
+            )
             .add_systems(
                 (
                     reset.run_if(on_event::<RestartEvent>()),
                     apply_system_buffers,
                 )
             )
-            .add_systems((scroll_events, keyboard_input))
+            .add_system(cancel.run_if(on_event::<CancelEvent>()))
+            .add_system(layout::save_layout.run_if(on_event::<SaveLayoutEvent>()))
+            .add_system(layout::load_layout.run_if(on_event::<LoadLayoutEvent>()))
+            .add_system(export::export_ascii.run_if(on_event::<ExportAsciiEvent>()))
+            .add_system(playback::control_playback.run_if(on_event::<PlaybackEvent>()))
+            .add_system(history::apply_history.run_if(on_event::<HistoryEvent>()))
+            .add_system(manual::select_piece.run_if(on_event::<SelectPieceEvent>()))
+            .add_systems((manual::place_manually, manual::update_ghost).chain())
+            .add_systems((hint::clear_hints, hint::show_hints.run_if(on_event::<HintEvent>())).chain())
+            .add_systems((
+                scroll_events,
+                keyboard_input,
+                export::export_shortcuts,
+                playback::playback_shortcuts,
+                history::history_shortcuts,
+                manual::manual_shortcuts,
+                hint::hint_shortcuts,
+            ))
         ;
     }
 }
@@ -62,128 +103,101 @@ pub struct PlaceShapeEvent {
     pos: GlobalPos,
 }
 
-#[derive(Resource)]
-pub struct BoardState {
-    scored_positions: HashMap<GlobalPos, i32>,
-    bounds: Bounds,
-    attempts: usize,
-    max_attempts: usize,
-}
+/// Draw the solver's obstacles, like the walls of a box.
+pub struct DrawObstaclesEvent {}
 
-impl BoardState {
-    fn is_taken(&self, pos: &GlobalPos) -> bool {
-        self.scored_positions.get(pos).map_or(true, |x| *x == BLOCKED)
-    }
-}
+#[derive(Resource, Deref, DerefMut)]
+pub struct SolverState(Solver);
 
-const INITIAL: GlobalPos = GlobalPos {
-    x: (CHUNK_SIZE / 2) as i32,
-    y: (CHUNK_SIZE / 2) as i32,
-};
+/// What the shown board was made from, beyond what the solver itself knows.
+#[derive(Resource, Default)]
+pub struct RunConfig {
+    pub n: u16,
+    pub pieces: Option<String>,
+    pub counts: Vec<Option<u16>>,
+    pub settings: StrategySettings,
+}
 
-const BLOCKED: i32 = i32::MIN;
+fn start_solve(mut restart: EventWriter<RestartEvent>) {
+    restart.send(RestartEvent { mode: RestartMode::Solve });
+}
 
-fn find_best_shape(
-    mut state: ResMut<BoardState>,
-    bag: Res<ShapeBag>,
-    mut border_query: Query<&mut BorderTile>,
+/// Replays what the background solve found, as far as playback lets it.
+#[allow(clippy::too_many_arguments)]
+fn receive_placements(
+    mut solver: ResMut<SolverState>,
+    mut solve_task: ResMut<SolveTask>,
+    mut playback: ResMut<Playback>,
     mut place_shape_event: EventWriter<PlaceShapeEvent>,
+    mut draw_obstacles: EventWriter<DrawObstaclesEvent>,
+    mut commands: Commands,
+    chunk_manager: Res<ChunkManager>,
+    mut tilemap: Query<&mut TileStorage>,
 ) {
-    if state.scored_positions.len() == 0 {
-
-        // degenerate case: just place any ole tile first.
-        if let Some(permutation) = bag.get_random_permutation() {
-            place_shape_event.send(PlaceShapeEvent {
-                permutation,
-                pos: INITIAL.clone(),
-            });
-        }
-
-
-    } else {
-
-        let best_positions = collect_candidate_positions(
-            &border_query,
-            state.max_attempts > 0,
-        );
-        let mut attempts_count = 0;
-        let mut best_attempts = Vec::new();
-        
-        // Iterate every edge position
-        let mut dead_candidates = HashSet::new();
+    // polled every frame, only showing something counts as a change
+    solve_task.bypass_change_detection().receive();
 
-        'outer: for border_pos in best_positions.iter() {
-        
-            let mut any_match = false;
-            for shape in bag.iter_available() {
-            
-                for permutation_index in 0..shape::PERMUTATIONS {
-                    let permutation = ShapePermutation {
-                        index: shape.index,
-                        permutation: Permutation::from_index(permutation_index),
-                    };
-
-                    // Iterate every position in the shape as anchor
-                    let shape_positions = bag.iter_pos(&permutation);
-
-                    for shape_tile_pos in &shape_positions {
-                        
-                        let attempt_pos = *border_pos - *shape_tile_pos;
-                        
-                        // early out if we hit maximum crunch
-                        if state.max_attempts > 0 
-                            && attempts_count > state.max_attempts 
-                            && best_attempts.len() > 1 {
-                            break 'outer;
-                        }
-
-                        attempts_count += 1;
-        
-                        if let Some(score) = get_placement_score(
-                            &attempt_pos,
-                            &shape_positions,
-                            &state,
-                        ) {
-                            any_match = true;
-                            best_attempts.push((score, permutation.clone(), attempt_pos));
-                        }
-                    }
-                }
-            }
+    // placements before the last replay would be cleared right away
+    let replay_index = solve_task.pending
+        .iter()
+        .rposition(|update| matches!(update, SolveUpdate::Replay(_)));
 
-            if !any_match {
-                dead_candidates.insert(*border_pos);
+    if let Some(replay_index) = replay_index.filter(|index| *index > 0) {
+        let skipped: Vec<SolveUpdate> = solve_task.pending.drain(..replay_index).collect();
+        for update in skipped {
+            if let SolveUpdate::RunFinished { area, best_area } = update {
+                solve_task.finish_run(area, best_area);
             }
         }
+    }
 
-        // Take best attempt...
-        best_attempts.sort_by_key(|x| x.0);
-        if let Some((_, permutation, attempt_pos)) = best_attempts.pop() {
-            place_shape_event.send(PlaceShapeEvent {
-                permutation,
-                pos: attempt_pos,
-            });
+    while let Some(update) = solve_task.pending.front() {
+        // a rewound board waits until it's back at the end of its history
+        let shown = match update {
+            SolveUpdate::Placed { .. } => !solver.can_redo() && playback.bypass_change_detection().take(),
+            SolveUpdate::Replay(_) => !solver.can_redo() && !playback.is_held(),
+            SolveUpdate::RunFinished { .. } => true,
+        };
+        if !shown {
+            break;
         }
 
-        if dead_candidates.len() > 0 {
-            for mut border_tile in border_query.iter_mut() {
-                if dead_candidates.contains(&border_tile.global_pos) {
-                    border_tile.dead = true;
+        match solve_task.pending.pop_front().expect("Front just checked.") {
+            SolveUpdate::Placed { placement, attempts } => {
+                solver.place(&placement);
+                solver.board.attempts = attempts;
+
+                if solve_task.limit.is_none() {
+                    solve_task.placed += 1;
                 }
-            }
-        }
 
-        state.attempts += attempts_count;
+                place_shape_event.send(PlaceShapeEvent {
+                    permutation: placement.permutation,
+                    pos: placement.pos,
+                });
+            },
+            SolveUpdate::RunFinished { area, best_area } => {
+                solve_task.finish_run(area, best_area);
+            },
+            SolveUpdate::Replay(replay) => {
+                clear_tiles(&solver, &mut commands, &chunk_manager, &mut tilemap);
+                solver.0 = *replay;
+                redraw_tiles(&solver, &mut place_shape_event, &mut draw_obstacles);
+            },
+        }
     }
 
+    if solve_task.is_finished() {
+        solve_task.finish();
+    }
 }
 
 fn place_shape(
     mut tilemap: Query<&mut TileStorage>,
-    mut bag: ResMut<ShapeBag>,
+    solver: Res<SolverState>,
     mut place_shape_events: EventReader<PlaceShapeEvent>,
+    mut draw_obstacles_events: EventReader<DrawObstaclesEvent>,
     mut commands: Commands,
-    mut state: ResMut<BoardState>,
     mut chunk_manager: ResMut<ChunkManager>,
     asset_server: Res<AssetServer>,
 ) {
@@ -198,7 +212,7 @@ fn place_shape(
         tiles_per_chunk: &mut HashMap<IVec2, Vec<(TilePos, usize)>>,
         global_pos: &GlobalPos,
     ) {
-        let (chunk_pos, tile_pos) = global_pos.to_chunk_pos();
+        let (chunk_pos, tile_pos) = to_chunk_pos(global_pos);
 
         match tiles_per_chunk.entry(chunk_pos) {
             Entry::Occupied(mut entry) => {
@@ -210,30 +224,34 @@ fn place_shape(
         }
     }
 
+    // tiles.png has one color per original shape, bigger piece sets reuse them
+    const SHAPE_TEXTURES: usize = 6;
+
     for place_shape_event in place_shape_events.iter() {
 
         let shape = &place_shape_event.permutation;
         let attempt_pos = place_shape_event.pos;
 
-        if !bag.try_pop(shape.index) {
-            panic!("Tried to place shape that was unavailable.");
-        }
-
-        for shape_pos in bag.iter_pos(&shape) {
+        for shape_pos in solver.bag.iter_pos(shape) {
             
             let global_pos = shape_pos + attempt_pos;
-            place_tile(shape.index, &mut tiles_per_chunk, &global_pos);
+            place_tile(shape.index % SHAPE_TEXTURES, &mut tiles_per_chunk, &global_pos);
        
             for neighbor_pos in iter_moore(global_pos) {
                 border.insert(neighbor_pos);
             }
-    
-            state.bounds.expand(&global_pos);
+        }
+    }
 
-            if let Some(prev) = state.scored_positions.insert(global_pos, BLOCKED) {
-                if prev == BLOCKED {
-                    panic!("Overlapped old tile!");
-                }
+    // obstacles share the border texture, tinted dark
+    const OBSTACLE_INDEX: usize = 7;
+
+    if draw_obstacles_events.iter().count() > 0 {
+        for global_pos in &solver.board.obstacles {
+            place_tile(OBSTACLE_INDEX, &mut tiles_per_chunk, global_pos);
+
+            for neighbor_pos in iter_moore(*global_pos) {
+                border.insert(neighbor_pos);
             }
         }
     }
@@ -242,9 +260,8 @@ fn place_shape(
     const BORDER_INDEX: usize = 6;
     for border_pos in border.iter() {
 
-        //let (chunk_pos, neighbor) = to_chunk_pos(&border_pos);
-        if !state.scored_positions.contains_key(&border_pos) {
-            place_tile(BORDER_INDEX, &mut tiles_per_chunk, &border_pos);
+        if solver.board.border.contains_key(border_pos) {
+            place_tile(BORDER_INDEX, &mut tiles_per_chunk, border_pos);
         }
     }
 
@@ -259,24 +276,27 @@ fn place_shape(
 
             //println!("Placing tile {} at {}, {}", shape_index, tile_pos.x, tile_pos.y);
 
+            let (texture_index, color) = if shape_index == OBSTACLE_INDEX {
+                (BORDER_INDEX, Color::rgb(0.2, 0.2, 0.2))
+            } else {
+                (shape_index, Color::Rgba { red: 1., green: 1., blue: 1., alpha: 1. })
+            };
+
             // in with the new
             let mut new_tile_commands = commands
-                .spawn((
+                .spawn(
                     TileBundle {
                         position: tile_pos,
                         tilemap_id: TilemapId(tilemap_entity),
-                        texture_index: TileTextureIndex(shape_index as u32),
-                        color: TileColor(Color::Rgba { red: 1., green: 1., blue: 1., alpha: 1. }),
+                        texture_index: TileTextureIndex(texture_index as u32),
+                        color: TileColor(color),
                         ..Default::default()
                     },
-                ));
+                );
 
             if shape_index == BORDER_INDEX {
                 new_tile_commands.insert(BorderTile {
-                    adjacency_score: 0,
-                    distance_score: 0,
-                    global_pos: GlobalPos::from_chunk_tile(&chunk_pos, &tile_pos),
-                    dead: false
+                    global_pos: from_chunk_tile(&chunk_pos, &tile_pos),
                 });
             }
 
@@ -285,7 +305,7 @@ fn place_shape(
             placed.push((tile_pos, new_tile_id));
             
         }
-        return placed;
+        placed
     }
 
     // Then, step through all chunks and allocate tiles in the right chunk
@@ -343,197 +363,103 @@ fn place_shape(
 }
 
 fn update_boundary_score(
-    mut state: ResMut<BoardState>,
-    mut border_query: Query<(&mut BorderTile, &mut TileColor)>,
+    solver: Res<SolverState>,
+    mut border_query: Query<(&BorderTile, &mut TileColor)>,
 ) {
 
     const MAX_ADJACENCY_SCORE: f32 = 4.;
+    const MAX_DISTANCE_SCORE: f32 = 10.;
 
-    let center_of_mass = INITIAL.clone();   // todo
-
-    if state.bounds.is_default() {
+    if !solver.is_changed() {
         return;
     }
 
-    let max_distance = ((state.bounds.width().pow(2) + state.bounds.height().pow(2)) as f32).sqrt();
-
-    for (mut border, mut color) in border_query.iter_mut() {
+    for (border_tile, mut color) in border_query.iter_mut() {
 
-        border.adjacency_score = 0;
-        for neighbor in iter_moore(border.global_pos) {
-            if state.is_taken(&neighbor) {
-                border.adjacency_score += 1;
-            }
-        }
-
-        let distance_x = (border.global_pos.x - center_of_mass.x).abs() as f32;
-        let distance_y = (border.global_pos.y - center_of_mass.y).abs() as f32;
-        let distance = (distance_x.powi(2) + distance_y.powi(2)).sqrt();
-
-        let normalized_distance = (max_distance - distance) / max_distance;
-        border.distance_score = (normalized_distance * 10.) as i32;
-        
-        // calculate adjacency score and update color
-        color.0 = Color::rgba(
-            border.adjacency_score as f32 / MAX_ADJACENCY_SCORE, 
-            normalized_distance,
-            0.,
-            1.);
-
-        let score = border.distance_score + border.adjacency_score;
-        if let Some(prev) = state.scored_positions.insert(border.global_pos, score) {
-            if prev == BLOCKED {
-                panic!("Overwrote blocked position at {}, {}", border.global_pos.x, border.global_pos.y);
-            }
+        if let Some(border) = solver.board.border.get(&border_tile.global_pos) {
+            color.0 = Color::rgba(
+                border.adjacency_score as f32 / MAX_ADJACENCY_SCORE, 
+                border.distance_score as f32 / MAX_DISTANCE_SCORE,
+                0.,
+                1.);
         }
     }
 }
 
-
-fn collect_candidate_positions(
-    border_query: &Query<&mut BorderTile>,
-    sort_best: bool,
-) -> Vec<GlobalPos> {
-
-    let mut border_tiles: Vec<&BorderTile> = border_query
-        .iter()
-        .filter(|border | !border.dead)
-        .collect();
-
-    if sort_best {
-        border_tiles.sort_by(|a, b| b.score().cmp(&a.score()));
-    }
-
-    let mut border: Vec<GlobalPos> = border_tiles
-        .iter()
-        .map(|border| border.global_pos.clone())
-        .collect();
-
-    if border.len() == 0 {
-
-        // start at center...
-        let x = (CHUNK_SIZE / 2) as i32;
-        let y = (CHUNK_SIZE / 2) as i32;
-
-        border.push(GlobalPos { x, y });
-    }
-
-    return border;
+pub struct RestartEvent {
+    pub mode: RestartMode,
 }
 
-fn iter_moore(tile_pos: GlobalPos) -> impl Iterator<Item = GlobalPos> {
-    const NEIGHBORHOOD: [(i32, i32); 4] = [
-        ( 1, 0),
-        ( 0, 1),
-        (-1, 0),
-        ( 0,-1),
-    ];
-
-    NEIGHBORHOOD.iter().map(move |xy| GlobalPos { 
-        x: (tile_pos.x + xy.0),
-        y: (tile_pos.y + xy.1),
-     })
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum RestartMode {
+    /// Solve a new board.
+    Solve,
+    /// Start an empty board to place pieces on by hand.
+    Manual,
+    /// Keep the pieces on the board and solve the rest around them.
+    Continue,
 }
 
-fn get_placement_score(
-    offset: &GlobalPos,
-    shape_positions: &Vec<GlobalPos>,
-    state: &BoardState,
-) -> Option<i32> {
-
-    let mut score_sum = 0;
+pub struct CancelEvent {}
 
-    let mut expanded_bounds = state.bounds.clone();
-
-    for tile_pos in shape_positions { 
-        let global_pos = *tile_pos + *offset;
-        if let Some(score) = state.scored_positions.get(&global_pos) {
-            if *score == BLOCKED {
-                return None;
-            }
-            score_sum += score;
-        }
+fn cancel(mut solve_task: ResMut<SolveTask>) {
+    solve_task.cancel();
+}
 
-        expanded_bounds.expand(&global_pos);
-    }
+/// Despawns every tile drawn for the solver's board, placed and border alike.
+/// Tiles still waiting in a draw event, like right after a reset, are skipped.
+fn clear_tiles(
+    solver: &Solver,
+    commands: &mut Commands,
+    chunk_manager: &ChunkManager,
+    tilemap: &mut Query<&mut TileStorage>,
+) {
+    for global_pos in solver.board.scored_positions.keys() {
 
-    // subtract score for total bounds size?
-    let original_area = state.bounds.width() * state.bounds.height();
-    let area = expanded_bounds.width() * expanded_bounds.height();
-    let area_penalty = area - original_area;
-
-    score_sum -= area_penalty;
-
-    // sum empty tiles in bounds...?
-    if false {
-        let mut emptiness = 0;
-        for x in expanded_bounds.min_x..expanded_bounds.max_x + 1 {
-            for y in expanded_bounds.min_y..expanded_bounds.max_y + 1 {
-                let pos = GlobalPos { x, y };
-                if state.is_taken(&pos) {
-                    emptiness += 1;
-                } else {
-                    emptiness -= 1;
-                }
-            }
-        }
+        let (chunk_pos, tile_pos) = to_chunk_pos(global_pos);
 
-        score_sum += emptiness;
-    }
+        let Some(tile_storage_entity) = chunk_manager.spawned_chunks.get(&chunk_pos) else {
+            continue;
+        };
 
-    return Some(score_sum);
-}
+        // a chunk spawned this frame only gets its storage once commands apply
+        let Ok(mut tile_storage) = tilemap.get_mut(*tile_storage_entity) else {
+            continue;
+        };
 
-#[derive(Clone)]
-pub struct Bounds {
-    min_x: i32,
-    min_y: i32,
-    max_x: i32,
-    max_y: i32,
-}
-
-impl Bounds {
-    fn new() -> Bounds {
-        Bounds {
-            min_x: i32::MAX,
-            max_x: i32::MIN,
-            min_y: i32::MAX,
-            max_y: i32::MIN,
+        if let Some(entity) = tile_storage.get(&tile_pos) {
+            commands.entity(entity).despawn_recursive();
+            tile_storage.remove(&tile_pos);
         }
     }
+}
 
-    fn is_default(&self) -> bool {
-        self.min_x == i32::MAX &&
-        self.max_x == i32::MIN &&
-        self.min_y == i32::MAX &&
-        self.max_y == i32::MIN
-
-    }
+/// Queues everything on the solver's board to be drawn, after `clear_tiles`.
+fn redraw_tiles(
+    solver: &Solver,
+    place_shape_event: &mut EventWriter<PlaceShapeEvent>,
+    draw_obstacles: &mut EventWriter<DrawObstaclesEvent>,
+) {
+    draw_obstacles.send(DrawObstaclesEvent {});
 
-    fn width(&self) -> i32 {
-        self.max_x - self.min_x + 1
+    for placement in &solver.placements {
+        place_shape_event.send(PlaceShapeEvent {
+            permutation: placement.permutation,
+            pos: placement.pos,
+        });
     }
-    
-    fn height(&self) -> i32 {
-        self.max_y - self.min_y + 1
-    }
-
-    fn expand(&mut self, global_pos: &GlobalPos) {
-
-        self.max_x = global_pos.x.max(self.max_x);
-        self.max_y = global_pos.y.max(self.max_y);
-        self.min_x = global_pos.x.min(self.min_x);
-        self.min_y = global_pos.y.min(self.min_y);
-    }    
 }
 
-
-pub struct RestartEvent {}
-
+#[allow(clippy::too_many_arguments)]
 fn reset(
-    mut shapes: ResMut<ShapeBag>,
+    mut restart_events: EventReader<RestartEvent>,
+    mut solver: ResMut<SolverState>,
+    mut solve_task: ResMut<SolveTask>,
+    mut manual: ResMut<ManualPlay>,
+    mut run_config: ResMut<RunConfig>,
+    mut place_shape_event: EventWriter<PlaceShapeEvent>,
+    mut draw_obstacles: EventWriter<DrawObstaclesEvent>,
     mut commands: Commands,
-    mut state: ResMut<BoardState>,
     chunk_manager: ResMut<ChunkManager>,
     mut tilemap: Query<&mut TileStorage>,
     input_fields: Query<&InputFieldsState>,
@@ -541,46 +467,151 @@ fn reset(
 
     println!("=== RESET ===");
 
-    //let (tile_storage_entity, mut tile_storage) = tilemap
-    //    .get_single_mut().expect("Need a tilemap");
-
-    // clear tiles
-    for (global_pos, _score) in state.scored_positions.drain() {
-
-        let (chunk_pos, tile_pos) = global_pos.to_chunk_pos();
-
-        let tile_storage_entity = chunk_manager.spawned_chunks.get(&chunk_pos).unwrap();
+    let mode = restart_events.iter().last().map_or(RestartMode::Solve, |restart| restart.mode);
+    let locked = match mode {
+        RestartMode::Continue => solver.placements.clone(),
+        _ => Vec::new(),
+    };
 
-        let mut tile_storage = tilemap.get_mut(*tile_storage_entity).unwrap();
-
-        if let Some(entity) = tile_storage.get(&tile_pos) {
-            commands.entity(entity).despawn_recursive();
-            tile_storage.remove(&tile_pos);
-        } else {
-            panic!("Could not retrieve tile from storage");
-        }
-    }
+    clear_tiles(&solver, &mut commands, &chunk_manager, &mut tilemap);
 
     // apparently we get one state per input widget
     // but whatever
     let mut count = 1;
     let mut max_attempts = 0;
-    for input_field in input_fields.iter() {
+    let mut pieces = String::new();
+    let mut counts = Vec::new();
+    let mut strategy = StrategyKind::Greedy;
+    let mut settings = StrategySettings::default();
+    let mut weights = ScoreWeights::default();
+    let mut seed = None;
+    let mut restarts = None;
+    let mut container = None;
+    let mut mask = None;
+    if let Some(input_field) = input_fields.iter().next() {
         if let Ok(n) = input_field.n.parse::<u16>() {
             count = n;
         }
         if let Ok(crunch) = input_field.crunch.parse::<usize>() {
             max_attempts = crunch;
         }
-        break;
+        if let Ok(beam) = input_field.beam.parse::<usize>() {
+            settings.beam_width = beam;
+        }
+        match input_field.strategy.parse::<StrategyKind>() {
+            Ok(kind) => strategy = kind,
+            Err(message) => println!("{}", message),
+        }
+        match input_field.weights.parse::<ScoreWeights>() {
+            Ok(parsed) => weights = parsed,
+            Err(message) => println!("{}", message),
+        }
+        pieces = input_field.pieces.trim().to_string();
+        match ShapeBag::parse_counts(&input_field.counts) {
+            Ok(parsed) => counts = parsed,
+            Err(message) => println!("{}", message),
+        }
+        if !input_field.seed.trim().is_empty() {
+            match input_field.seed.trim().parse::<u64>() {
+                Ok(parsed) => seed = Some(parsed),
+                Err(_) => println!("Invalid seed {}", input_field.seed.trim()),
+            }
+        }
+        if !input_field.restarts.trim().is_empty() {
+            match input_field.restarts.parse::<RestartLimit>() {
+                Ok(limit) => restarts = Some(limit),
+                Err(message) => println!("{}", message),
+            }
+        }
+        if !input_field.container.trim().is_empty() {
+            match input_field.container.parse::<BoxSize>() {
+                Ok(size) => container = Some(size),
+                Err(message) => println!("{}", message),
+            }
+        }
+        if !input_field.mask.trim().is_empty() {
+            if container.is_some() {
+                println!("Box and mask can't be combined, using the box");
+            } else {
+                match BoardMask::from_file(input_field.mask.trim()) {
+                    Ok(parsed) => mask = Some(parsed),
+                    Err(message) => println!("{}", message),
+                }
+            }
+        }
     }
 
-    shapes.reset(count);
+    // an empty seed field picks a new one every restart
+    let seed = seed.unwrap_or_else(rand::random);
+
+    let mut pieces = if pieces.is_empty() { None } else { Some(pieces) };
+    let mut bag = match &pieces {
+        None => ShapeBag::load(count),
+        Some(path) => ShapeBag::from_file(path, count).unwrap_or_else(|message| {
+            println!("{}", message);
+            pieces = None;
+            ShapeBag::load(count)
+        }),
+    };
+
+    if let Err(message) = bag.set_counts(&counts) {
+        println!("{}", message);
+        counts.clear();
+    }
 
-    //state.scored_positions = HashMap::new();
-    state.attempts = 0;
-    state.max_attempts = max_attempts;
-    state.bounds = Bounds::new();
+    *run_config = RunConfig {
+        n: count,
+        pieces,
+        counts,
+        settings: settings.clone(),
+    };
+
+    let empty_board = move || {
+        let solver = Solver::new(bag.clone(), max_attempts);
+        match (container, &mask) {
+            (Some(size), _) => solver.with_container(size),
+            (None, Some(mask)) => solver.with_mask(mask),
+            (None, None) => solver,
+        }
+    };
+
+    // pieces from the old settings may not fit the new ones
+    let locked = match empty_board().with_locked(&locked) {
+        Ok(_) => locked,
+        Err(message) => {
+            println!("{}", message);
+            Vec::new()
+        },
+    };
+
+    // the background solve searches, ours only replays what it finds
+    let build_solver = move |seed| {
+        let mut solver = empty_board()
+            .with_strategy(strategy.build(&settings))
+            .with_seed(seed)
+            .with_locked(&locked)
+            .expect("Locked placements were checked.");
+        solver.board.weights = weights.clone();
+        solver
+    };
+
+    solver.0 = build_solver(seed);
+    redraw_tiles(&solver, &mut place_shape_event, &mut draw_obstacles);
+
+    *manual = ManualPlay {
+        active: mode == RestartMode::Manual,
+        selected: solver.bag.iter_available().next().map(|shape| shape.index),
+        ..default()
+    };
+    if manual.active {
+        solve_task.show_manual(solver.bag.remaining_total());
+        return;
+    }
+
+    match restarts {
+        Some(limit) => solve_task.start_restarts(seed, limit, build_solver),
+        None => solve_task.start(build_solver(seed)),
+    }
 }
 
 fn scroll_events(
@@ -634,4 +665,75 @@ fn keyboard_input(
         camera.translation.y += y;
         //camera.scale.z = zoom;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(TilePlugin)
+            .add_event::<RestartEvent>()
+            .add_event::<PlaceShapeEvent>()
+            .add_event::<DrawObstaclesEvent>()
+            .insert_resource(SolverState(Solver::new(ShapeBag::load(1), 0)))
+            .init_resource::<SolveTask>()
+            .init_resource::<RunConfig>()
+            .init_resource::<Playback>()
+            .init_resource::<ManualPlay>()
+            .add_systems(
+                (
+                    playback::advance_playback,
+                    receive_placements,
+                    place_shape,
+                )
+                .chain()
+                .in_base_set(CoreSet::PreUpdate)
+            )
+            .add_system(reset.run_if(on_event::<RestartEvent>()));
+        app
+    }
+
+    fn is_drawn(app: &App, global_pos: &GlobalPos) -> bool {
+        let (chunk_pos, tile_pos) = to_chunk_pos(global_pos);
+        let chunk_manager = app.world.resource::<ChunkManager>();
+
+        chunk_manager.spawned_chunks
+            .get(&chunk_pos)
+            .and_then(|entity| app.world.get::<TileStorage>(*entity))
+            .is_some_and(|tile_storage| tile_storage.get(&tile_pos).is_some())
+    }
+
+    fn solved() -> Solver {
+        let mut solver = Solver::new(ShapeBag::load(1), 0).with_seed(1);
+        solver.solve();
+        solver
+    }
+
+    #[test]
+    fn replay_right_after_a_reset_skips_undrawn_tiles() {
+        let mut app = test_app();
+
+        app.insert_resource(SolverState(solved()));
+        for placement in &app.world.resource::<SolverState>().placements.clone() {
+            app.world.send_event(PlaceShapeEvent { permutation: placement.permutation, pos: placement.pos });
+        }
+        app.update();
+
+        // the reset only queues the pieces it keeps to be drawn...
+        app.world.send_event(RestartEvent { mode: RestartMode::Continue });
+        app.update();
+
+        // ...and a replay is received before they are
+        let replay = Solver::new(ShapeBag::load(1), 0).with_locked(&solved().placements).unwrap();
+        app.world.resource_mut::<SolveTask>().pending.push_back(SolveUpdate::Replay(Box::new(replay)));
+        app.update();
+
+        let solver = app.world.resource::<SolverState>();
+        assert!(app.world.resource::<SolveTask>().pending.is_empty());
+        assert!(solver.iter_tiles().all(|(_, global_pos)| is_drawn(&app, &global_pos)));
+    }
+}
@@ -0,0 +1,143 @@
+//! Holds placements from the background solve back, so they can be shown at a
+//! steady pace, paused and stepped through one at a time.
+
+use bevy::prelude::*;
+use kayak_ui::prelude::widgets::TextBoxState;
+
+use super::{
+    task::SolveTask,
+    ui::{is_typing, InputFieldsState},
+};
+
+pub enum PlaybackEvent {
+    Play,
+    Pause,
+    Toggle,
+    Step,
+}
+
+#[derive(Resource)]
+pub struct Playback {
+    pub playing: bool,
+    /// Pieces per second, `None` shows each one as soon as it's found.
+    pub speed: Option<f32>,
+    /// Pieces the speed allows by now, fractions carry over between frames.
+    budget: f32,
+    /// Steps asked for that haven't been shown yet.
+    steps: usize,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            playing: true,
+            speed: None,
+            budget: 0.,
+            steps: 0,
+        }
+    }
+}
+
+impl Playback {
+    /// Whether the next placement may be shown now, using up a step or some budget if so.
+    pub fn take(&mut self) -> bool {
+        if self.steps > 0 {
+            self.steps -= 1;
+            return true;
+        }
+
+        if !self.playing {
+            return false;
+        }
+
+        match self.speed {
+            None => true,
+            Some(_) if self.budget >= 1. => {
+                self.budget -= 1.;
+                true
+            },
+            Some(_) => false,
+        }
+    }
+
+    /// Paused with no steps waiting, so nothing on the board should change.
+    pub fn is_held(&self) -> bool {
+        !self.playing && self.steps == 0
+    }
+
+    pub fn label(&self) -> String {
+        match (self.playing, self.speed) {
+            (false, _) => "Paused".into(),
+            (true, None) => "Playing at full speed".into(),
+            (true, Some(speed)) => format!("Playing at {} pieces/s", speed),
+        }
+    }
+}
+
+/// Space plays or pauses, N or period shows a single piece.
+pub fn playback_shortcuts(
+    keys: Res<Input<KeyCode>>,
+    text_boxes: Query<&TextBoxState>,
+    mut playback_events: EventWriter<PlaybackEvent>,
+) {
+    if is_typing(&text_boxes) {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        playback_events.send(PlaybackEvent::Toggle);
+    }
+
+    if keys.any_just_pressed([KeyCode::N, KeyCode::Period]) {
+        playback_events.send(PlaybackEvent::Step);
+    }
+}
+
+pub fn control_playback(
+    mut playback_events: EventReader<PlaybackEvent>,
+    mut playback: ResMut<Playback>,
+) {
+    for event in playback_events.iter() {
+        match event {
+            PlaybackEvent::Play => playback.playing = true,
+            PlaybackEvent::Pause => playback.playing = false,
+            PlaybackEvent::Toggle => playback.playing = !playback.playing,
+            PlaybackEvent::Step => {
+                // stepping only makes sense while paused
+                playback.playing = false;
+                playback.steps += 1;
+            },
+        }
+    }
+}
+
+/// Picks up the speed field and adds this frame's share of pieces to the budget.
+pub fn advance_playback(
+    time: Res<Time>,
+    solve_task: Res<SolveTask>,
+    input_fields: Query<&InputFieldsState>,
+    mut playback: ResMut<Playback>,
+) {
+    let speed = input_fields
+        .iter()
+        .next()
+        .and_then(|input_field| input_field.speed.trim().parse::<f32>().ok())
+        .filter(|speed| *speed > 0.);
+
+    if playback.speed != speed {
+        playback.speed = speed;
+        playback.budget = 0.;
+    }
+
+    // the budget changes every frame, only speed and pausing are worth redrawing for
+    let playback = playback.bypass_change_detection();
+
+    if let (true, Some(speed)) = (playback.playing, playback.speed) {
+        playback.budget += speed * time.delta_seconds();
+    }
+
+    // waiting on the solver shouldn't save up a burst of pieces
+    if solve_task.pending.is_empty() {
+        playback.budget = playback.budget.min(1.);
+    }
+}
@@ -0,0 +1,263 @@
+//! Runs the solver on the async compute pool, so a high crunch doesn't freeze
+//! rendering. Placements come back over a channel and are replayed on the
+//! `SolverState` the renderer looks at.
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use anders_kluring::solver::{Placement, RandomRestarts, RestartLimit, Solver};
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool, utils::Instant};
+
+pub enum SolveUpdate {
+    Placed {
+        placement: Placement,
+        /// Attempts the background solver had spent once it found `placement`.
+        attempts: usize,
+    },
+    /// A restart ran to the end.
+    RunFinished {
+        area: i32,
+        best_area: i32,
+    },
+    /// Show this empty solver instead, the placements that follow replay a new best run.
+    Replay(Box<Solver>),
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum SolveStatus {
+    #[default]
+    Idle,
+    Running,
+    /// Waiting for pieces to be placed by hand.
+    Manual,
+    Done,
+    Cancelled,
+}
+
+impl SolveStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SolveStatus::Idle => "Idle",
+            SolveStatus::Running => "Solving",
+            SolveStatus::Manual => "Your turn",
+            SolveStatus::Done => "Done",
+            SolveStatus::Cancelled => "Cancelled",
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SolveTask {
+    updates: Option<Mutex<Receiver<SolveUpdate>>>,
+    cancel: Arc<AtomicBool>,
+    /// Received but not shown yet, playback decides when.
+    pub pending: VecDeque<SolveUpdate>,
+    /// The background solve has sent everything it will.
+    sent_all: bool,
+    pub status: SolveStatus,
+    pub placed: usize,
+    pub total: usize,
+    /// Set while restarting, the stats below only count then.
+    pub limit: Option<RestartLimit>,
+    pub started: Option<Instant>,
+    pub runs: usize,
+    pub last_area: i32,
+    pub best_area: i32,
+}
+
+impl SolveTask {
+    /// Cancels the running solve, if any, and sets up for a new one.
+    fn begin(&mut self, total: usize, limit: Option<RestartLimit>) -> (mpsc::Sender<SolveUpdate>, Arc<AtomicBool>) {
+        self.cancel();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        *self = SolveTask {
+            updates: Some(Mutex::new(receiver)),
+            cancel: cancel.clone(),
+            status: SolveStatus::Running,
+            total,
+            limit,
+            started: Some(Instant::now()),
+            ..default()
+        };
+
+        (sender, cancel)
+    }
+
+    /// Cancels the running solve, if any, and starts `solver` in the background.
+    pub fn start(&mut self, solver: Solver) {
+        let (sender, cancel) = self.begin(solver.bag.remaining_total(), None);
+        let mut solver = solver.with_cancel(cancel.clone());
+
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                while !solver.is_done() && !cancel.load(Ordering::Relaxed) {
+                    let Some(placement) = solver.step() else {
+                        break;
+                    };
+
+                    let update = SolveUpdate::Placed {
+                        placement,
+                        attempts: solver.board.attempts,
+                    };
+                    if sender.send(update).is_err() {
+                        break;
+                    }
+
+                    YieldNow(false).await;
+                }
+            })
+            .detach();
+    }
+
+    /// Like `start`, but solves again with seeds `seed`, `seed + 1`, ... until
+    /// `limit` is hit. Only runs that beat the best so far are streamed.
+    pub fn start_restarts(
+        &mut self,
+        seed: u64,
+        limit: RestartLimit,
+        mut build: impl FnMut(u64) -> Solver + Send + 'static,
+    ) {
+        let total = match limit {
+            RestartLimit::Runs(runs) => runs.max(1),
+            RestartLimit::Time(_) => 0,
+        };
+        let (sender, cancel) = self.begin(total, Some(limit));
+
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                let started = Instant::now();
+                let run_cancel = cancel.clone();
+                let mut restarts = RandomRestarts::new(seed, move |seed| build(seed).with_cancel(run_cancel.clone()));
+
+                while limit.allows(restarts.runs, started.elapsed()) && !cancel.load(Ordering::Relaxed) {
+                    let (area, improved) = restarts.run();
+
+                    let mut updates = vec![SolveUpdate::RunFinished {
+                        area,
+                        best_area: restarts.best_area().unwrap_or(area),
+                    }];
+
+                    if improved {
+                        let best = restarts.best.as_ref().expect("Improved on nothing.");
+                        let attempts = best.board.attempts;
+                        let placements = best.placements.clone();
+
+                        // the replayed solver starts out with any locked placements
+                        let replay = restarts.build_best().expect("Improved on nothing.");
+                        let locked = replay.placements.len();
+
+                        updates.push(SolveUpdate::Replay(Box::new(replay)));
+                        updates.extend(placements.into_iter().skip(locked).map(|placement| SolveUpdate::Placed {
+                            placement,
+                            attempts,
+                        }));
+                    }
+
+                    if updates.into_iter().any(|update| sender.send(update).is_err()) {
+                        break;
+                    }
+
+                    YieldNow(false).await;
+                }
+            })
+            .detach();
+    }
+
+    /// Stops the background solve, even halfway through planning a board.
+    /// Placements already sent are dropped.
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.pending.clear();
+
+        if self.updates.take().is_some() {
+            self.status = SolveStatus::Cancelled;
+        }
+    }
+
+    /// Queues whatever the background solve sent since the last call.
+    pub fn receive(&mut self) {
+        let Some(updates) = &self.updates else {
+            return;
+        };
+
+        let updates = updates.lock().unwrap();
+        loop {
+            match updates.try_recv() {
+                Ok(update) => self.pending.push_back(update),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.sent_all = true;
+                    return;
+                },
+            }
+        }
+    }
+
+    /// Whether the solve ended and everything it sent has been shown.
+    pub fn is_finished(&self) -> bool {
+        self.updates.is_some() && self.sent_all && self.pending.is_empty()
+    }
+
+    /// Stops any running solve and reports a board that was loaded rather than solved.
+    pub fn show_loaded(&mut self, placed: usize) {
+        self.cancel();
+        *self = SolveTask {
+            status: SolveStatus::Done,
+            placed,
+            total: placed,
+            ..default()
+        };
+    }
+
+    /// Counts a restart that ran to the end.
+    pub fn finish_run(&mut self, area: i32, best_area: i32) {
+        self.runs += 1;
+        self.placed = self.runs;
+        self.last_area = area;
+        self.best_area = best_area;
+    }
+
+    /// Stops any running solve and counts pieces placed by hand instead.
+    pub fn show_manual(&mut self, total: usize) {
+        self.cancel();
+        *self = SolveTask {
+            status: SolveStatus::Manual,
+            total,
+            ..default()
+        };
+    }
+
+    pub fn finish(&mut self) {
+        self.updates = None;
+        self.status = SolveStatus::Done;
+    }
+}
+
+/// Pending once, so other tasks get a turn between steps. On wasm every task
+/// shares the main thread.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
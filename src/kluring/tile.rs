@@ -1,4 +1,5 @@
-use std::{collections::{HashMap}, ops::{Sub, Add}};
+use std::collections::HashMap;
+use anders_kluring::solver::GlobalPos;
 use bevy_ecs_tilemap::prelude::*;
 
 use bevy::prelude::*;
@@ -41,8 +42,8 @@ pub fn create_chunk(
     // get_tilemap_center_transform(&map_size, &grid_size, &map_type, 0.0)
     const CHUNK_SIZEF: f32 = CHUNK_SIZE as f32 * TILE_SIZE;
     let transform = Transform::from_translation(Vec3::new(
-        chunk_pos.x as f32 * CHUNK_SIZEF - CHUNK_SIZEF / 2.,
-        chunk_pos.y as f32 * CHUNK_SIZEF - CHUNK_SIZEF / 2.,
+        chunk_pos.x as f32 * CHUNK_SIZEF,
+        chunk_pos.y as f32 * CHUNK_SIZEF,
         0.0,
     ));
 
@@ -59,67 +60,35 @@ pub fn create_chunk(
     });
 }
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
-pub struct GlobalPos {
-    pub x: i32,
-    pub y: i32,
-}
-
-impl Add for GlobalPos {
-    type Output = GlobalPos;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        GlobalPos { x: self.x + rhs.x, y: self.y + rhs.y }
-    }
-}
-
-impl Sub for GlobalPos {
-    type Output = GlobalPos;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        GlobalPos { x: self.x - rhs.x, y: self.y - rhs.y }
+pub fn from_chunk_tile(chunk_pos: &IVec2, tile_pos: &TilePos) -> GlobalPos {
+    GlobalPos {
+        x: chunk_pos.x * CHUNK_SIZE as i32 + tile_pos.x as i32,
+        y: chunk_pos.y * CHUNK_SIZE as i32 + tile_pos.y as i32,
     }
 }
 
-impl GlobalPos {
-    pub fn from_chunk_tile(chunk_pos: &IVec2, tile_pos: &TilePos) -> GlobalPos {
-        GlobalPos {
-            x: chunk_pos.x * CHUNK_SIZE as i32 + tile_pos.x as i32,
-            y: chunk_pos.y * CHUNK_SIZE as i32 + tile_pos.y as i32,
+pub fn to_chunk_pos(global_pos: &GlobalPos) -> (IVec2, TilePos) {
+    const CHUNK_SIZEI: i32 = CHUNK_SIZE as i32;
+    let chunk_pos_x = global_pos.x.div_euclid(CHUNK_SIZEI);
+    let chunk_pos_y = global_pos.y.div_euclid(CHUNK_SIZEI);
+
+    let tile_pos_x = global_pos.x.rem_euclid(CHUNK_SIZEI);
+    let tile_pos_y = global_pos.y.rem_euclid(CHUNK_SIZEI);
+
+    (
+        IVec2 {
+            x: chunk_pos_x,
+            y: chunk_pos_y,
+        },
+        TilePos {
+            x: tile_pos_x as u32,
+            y: tile_pos_y as u32,
         }
-    }
-
-    pub fn to_chunk_pos(&self) -> (IVec2, TilePos) {
-        const CHUNK_SIZEI: i32 = CHUNK_SIZE as i32;
-        let chunk_pos_x = self.x.div_euclid(CHUNK_SIZEI);
-        let chunk_pos_y = self.y.div_euclid(CHUNK_SIZEI);
-    
-        let tile_pos_x = self.x.rem_euclid(CHUNK_SIZEI);
-        let tile_pos_y = self.y.rem_euclid(CHUNK_SIZEI);
-    
-        (
-            IVec2 {
-                x: chunk_pos_x,
-                y: chunk_pos_y,
-            },
-            TilePos {
-                x: tile_pos_x as u32,
-                y: tile_pos_y as u32,
-            }
-        )
-    }
+    )
 }
 
+/// Marks a rendered border tile; its score lives in the solver's `BoardState`.
 #[derive(Component)]
 pub struct BorderTile {
-    pub adjacency_score: i32,
-    pub distance_score: i32,
     pub global_pos: GlobalPos,
-    pub dead: bool,
 }
-
-impl BorderTile {
-    pub fn score(&self) -> i32 {
-        self.adjacency_score + self.distance_score
-    }
-}
\ No newline at end of file
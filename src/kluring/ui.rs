@@ -1,9 +1,19 @@
 use bevy::prelude::*;
 use kayak_ui::prelude::{widgets::*, KStyle, *, kayak_font::Alignment};
 
-use crate::kluring::RestartEvent;
+use anders_kluring::solver::{shape_letter, RestartLimit, ScoreWeights};
 
-use super::BoardState;
+use crate::kluring::{
+    export::ExportAsciiEvent,
+    hint::HintEvent,
+    history::HistoryEvent,
+    layout::{LoadLayoutEvent, SaveLayoutEvent},
+    manual::{ManualPlay, SelectPieceEvent},
+    playback::{Playback, PlaybackEvent},
+    CancelEvent, RestartEvent, RestartMode,
+};
+
+use super::{task::{SolveStatus, SolveTask}, SolverState};
 
 pub struct ShowUiPlugin;
 
@@ -25,22 +35,56 @@ pub struct StateWidgetProps {
     pub area_x: i32,
     pub area_y: i32,
     pub attempts: usize,
+    pub strategy: String,
+    pub seed: u64,
+    /// Restarts finished so far, 0 when not restarting.
+    pub runs: usize,
+    pub last_area: i32,
+    pub best_area: i32,
+    /// Size of the box being packed into, if any.
+    pub container: Option<(i32, i32)>,
+    /// Whether `container` is the bounding box of a mask.
+    pub masked: bool,
+    /// Pieces that didn't make it into the box once the solve is done.
+    pub left_over: Option<usize>,
 }
 
 fn statewidget_render(
     In((_widget_context, entity)): In<(KayakWidgetContext, Entity)>,
-    board_state: Res<BoardState>,
+    solver: Res<SolverState>,
+    solve_task: Res<SolveTask>,
     mut query: Query<(&mut StateWidgetProps, &KStyle, &mut ComputedStyles)>,
 ) -> bool {
     if let Ok((mut w, style, mut computed_styles)) = query.get_mut(entity) {
-        if board_state.bounds.is_default() {
+        if solver.board.bounds.is_default() {
             w.area_x = 0;
             w.area_y = 0;
             w.attempts = 0;
         } else {
-            w.area_x = board_state.bounds.width();
-            w.area_y = board_state.bounds.height();
-            w.attempts = board_state.attempts;
+            w.area_x = solver.board.bounds.width();
+            w.area_y = solver.board.bounds.height();
+            w.attempts = solver.board.attempts;
+        }
+        w.strategy = solver.strategy.name().into();
+        w.seed = solver.board.seed;
+        w.runs = solve_task.runs;
+        w.last_area = solve_task.last_area;
+        w.best_area = solve_task.best_area;
+        w.container = solver.board.container.as_ref().map(|container| (container.width(), container.height()));
+        w.masked = solver.board.masked;
+        w.left_over = (solve_task.status == SolveStatus::Done).then(|| solver.bag.remaining_total());
+
+        let mut content = format!("Area: {} ({} * {}) ({} attempts, {}, seed {})", w.area_x * w.area_y, w.area_x, w.area_y, w.attempts, w.strategy, w.seed);
+        if w.runs > 0 {
+            content += &format!("\nRun {}: area {}, best {}", w.runs, w.last_area, w.best_area);
+        }
+        if let Some((width, height)) = w.container {
+            content += &format!("\n{} {} * {}", if w.masked { "Mask" } else { "Box" }, width, height);
+            match w.left_over {
+                Some(0) => content += ": everything fits",
+                Some(left_over) => content += &format!(": {} left {} pieces", w.strategy, left_over),
+                None => {},
+            }
         }
 
         // Note: We will see two updates because of the mutable change to styles.
@@ -48,7 +92,7 @@ fn statewidget_render(
         *computed_styles = KStyle {
             font_size: StyleProp::Value(20.0),
             render_command: StyleProp::Value(RenderCommand::Text {
-                content: format!("Area: {} ({} * {}) ({} attempts)", w.area_x * w.area_y, w.area_x, w.area_y, w.attempts),
+                content,
                 alignment: Alignment::Start,
                 word_wrap: false,
                 subpixel: false,
@@ -63,15 +107,22 @@ fn statewidget_render(
 }
 
 // Our own version of widget_update that handles resource change events.
-pub fn widget_update_with_resource<
+pub fn widget_update_with_resources<
     Props: PartialEq + Component + Clone,
     State: PartialEq + Component + Clone,
 >(
     In((widget_context, entity, previous_entity)): In<(KayakWidgetContext, Entity, Entity)>,
-    my_resource: Res<BoardState>,
+    solver: Res<SolverState>,
+    solve_task: Res<SolveTask>,
+    playback: Res<Playback>,
+    manual: Res<ManualPlay>,
     widget_param: WidgetParam<Props, State>,
 ) -> bool {
-    widget_param.has_changed(&widget_context, entity, previous_entity) || my_resource.is_changed()
+    widget_param.has_changed(&widget_context, entity, previous_entity)
+        || solver.is_changed()
+        || solve_task.is_changed()
+        || playback.is_changed()
+        || manual.is_changed()
 }
 
 impl Widget for StateWidgetProps {}
@@ -95,6 +146,316 @@ impl Default for StateWidgetBundle {
     }
 }
 
+// ----- draw solve progress -----
+
+#[derive(Component, Default, Clone, PartialEq, Eq)]
+pub struct ProgressWidgetProps {
+    pub placed: usize,
+    pub total: usize,
+    pub status: String,
+    pub playback: String,
+}
+
+fn progresswidget_render(
+    In((_widget_context, entity)): In<(KayakWidgetContext, Entity)>,
+    solve_task: Res<SolveTask>,
+    playback: Res<Playback>,
+    mut query: Query<(&mut ProgressWidgetProps, &KStyle, &mut ComputedStyles)>,
+) -> bool {
+    const BAR_LENGTH: usize = 20;
+
+    if let Ok((mut w, style, mut computed_styles)) = query.get_mut(entity) {
+        w.placed = solve_task.placed;
+        w.total = solve_task.total;
+        w.status = solve_task.status.label().into();
+        w.playback = playback.label();
+
+        // restarting on a time budget, count time instead of runs
+        let (done, total, unit) = match solve_task.limit {
+            None => (w.placed, w.total, ""),
+            Some(RestartLimit::Runs(_)) => (w.placed, w.total, " runs"),
+            Some(RestartLimit::Time(budget)) => {
+                let elapsed = solve_task.started.map(|started| started.elapsed()).unwrap_or_default();
+                (elapsed.as_secs() as usize, budget.as_secs() as usize, "s")
+            },
+        };
+
+        let filled = (done.min(total) * BAR_LENGTH).checked_div(total).unwrap_or(0);
+        let bar = format!("{}{}", "#".repeat(filled), "-".repeat(BAR_LENGTH - filled));
+
+        *computed_styles = KStyle {
+            font_size: StyleProp::Value(20.0),
+            render_command: StyleProp::Value(RenderCommand::Text {
+                content: format!("{} [{}] {} / {}{}\n{}", w.status, bar, done.min(total), total, unit, w.playback),
+                alignment: Alignment::Start,
+                word_wrap: false,
+                subpixel: false,
+            }),
+            ..Default::default()
+        }
+        .with_style(style)
+        .into();
+    }
+
+    true
+}
+
+impl Widget for ProgressWidgetProps {}
+
+#[derive(Bundle)]
+pub struct ProgressWidgetBundle {
+    props: ProgressWidgetProps,
+    styles: KStyle,
+    computed_styles: ComputedStyles,
+    widget_name: WidgetName,
+}
+
+impl Default for ProgressWidgetBundle {
+    fn default() -> Self {
+        Self {
+            props: Default::default(),
+            styles: Default::default(),
+            computed_styles: Default::default(),
+            widget_name: ProgressWidgetProps::default().get_name(),
+        }
+    }
+}
+
+// ----- draw history timeline -----
+
+#[derive(Component, Default, Clone, PartialEq, Eq)]
+pub struct TimelineWidgetProps {
+    pub step: usize,
+    pub total: usize,
+}
+
+/// Most clickable cells across the timeline, kayak has no slider widget. Longer
+/// histories give each cell a range of placements.
+const TIMELINE_CELLS: usize = 50;
+
+fn timelinewidget_render(
+    In((widget_context, entity)): In<(KayakWidgetContext, Entity)>,
+    mut commands: Commands,
+    solver: Res<SolverState>,
+    mut query: Query<&mut TimelineWidgetProps>,
+) -> bool {
+    let Ok(mut w) = query.get_mut(entity) else {
+        return true;
+    };
+    w.step = solver.placements.len();
+    w.total = solver.history_len();
+
+    let (step, total) = (w.step, w.total);
+    let per_cell = total.div_ceil(TIMELINE_CELLS).max(1);
+    let cells = total.div_ceil(per_cell).max(1);
+    let filled = if step == total { cells } else { step / per_cell };
+
+    let label = if per_cell == 1 {
+        format!("Step {} / {} (Ctrl+Z, Ctrl+Y)", step, total)
+    } else {
+        format!("Step {} / {}, {} per cell (Ctrl+Z, Ctrl+Y for one)", step, total, per_cell)
+    };
+
+    let parent_id = Some(entity);
+    rsx! {
+        <ElementBundle styles={KStyle {
+            height: Units::Auto.into(),
+            bottom: Units::Pixels(20.0).into(),
+            ..Default::default()
+        }}>
+            <TextWidgetBundle
+                text={TextProps {
+                    alignment: Alignment::Start,
+                    content: label,
+                    size: 20.0,
+                    ..Default::default()
+                }}
+            />
+            <ElementBundle styles={KStyle {
+                layout_type: LayoutType::Row.into(),
+                height: Units::Pixels(20.0).into(),
+                col_between: Units::Pixels(2.0).into(),
+                ..Default::default()
+            }}>
+                {
+                    (0..cells).for_each(|cell| {
+                        // each cell jumps to the end of its range of placements
+                        let target = ((cell + 1) * per_cell).min(total);
+                        let color = if cell < filled {
+                            Color::rgb(0.35, 0.6, 0.9)
+                        } else {
+                            Color::rgb(0.25, 0.25, 0.3)
+                        };
+
+                        let on_event = OnEvent::new(
+                            move |In((event_dispatcher_context, _, event, _entity)): In<(
+                                EventDispatcherContext,
+                                WidgetState,
+                                KEvent,
+                                Entity,
+                            )>,
+                            mut history: EventWriter<HistoryEvent>| {
+                                match event.event_type {
+                                    EventType::Click(..) => history.send(HistoryEvent::Seek(target)),
+                                    // dragging across the cells scrubs
+                                    EventType::MouseIn(cursor) if cursor.pressed => history.send(HistoryEvent::Seek(target)),
+                                    _ => {},
+                                }
+                                (event_dispatcher_context, event)
+                            },
+                        );
+
+                        constructor! {
+                            <BackgroundBundle
+                                styles={KStyle {
+                                    background_color: StyleProp::Value(color),
+                                    width: Units::Stretch(1.0).into(),
+                                    height: Units::Stretch(1.0).into(),
+                                    cursor: KCursorIcon(CursorIcon::Hand).into(),
+                                    ..Default::default()
+                                }}
+                                on_event={on_event}
+                            />
+                        }
+                    });
+                }
+            </ElementBundle>
+        </ElementBundle>
+    };
+
+    true
+}
+
+impl Widget for TimelineWidgetProps {}
+
+#[derive(Bundle)]
+pub struct TimelineWidgetBundle {
+    props: TimelineWidgetProps,
+    styles: KStyle,
+    widget_name: WidgetName,
+}
+
+impl Default for TimelineWidgetBundle {
+    fn default() -> Self {
+        Self {
+            props: Default::default(),
+            styles: Default::default(),
+            widget_name: TimelineWidgetProps::default().get_name(),
+        }
+    }
+}
+
+// ----- draw manual play tray -----
+
+#[derive(Component, Default, Clone, PartialEq, Eq)]
+pub struct TrayWidgetProps {
+    /// Pieces left of every shape, empty outside manual play.
+    pub remaining: Vec<u16>,
+    pub selected: Option<usize>,
+}
+
+fn traywidget_render(
+    In((widget_context, entity)): In<(KayakWidgetContext, Entity)>,
+    mut commands: Commands,
+    solver: Res<SolverState>,
+    manual: Res<ManualPlay>,
+    mut query: Query<&mut TrayWidgetProps>,
+) -> bool {
+    let Ok(mut w) = query.get_mut(entity) else {
+        return true;
+    };
+    w.remaining = if manual.active {
+        (0..solver.bag.shape_count()).map(|index| solver.bag.remaining(index)).collect()
+    } else {
+        Vec::new()
+    };
+    w.selected = manual.selected;
+
+    let remaining = w.remaining.clone();
+    let selected = w.selected;
+
+    let parent_id = Some(entity);
+    rsx! {
+        <ElementBundle styles={KStyle {
+            layout_type: LayoutType::Row.into(),
+            height: Units::Auto.into(),
+            col_between: Units::Pixels(4.0).into(),
+            bottom: Units::Pixels(10.0).into(),
+            ..Default::default()
+        }}>
+            {
+                remaining.iter().enumerate().for_each(|(index, count)| {
+                    let color = if selected == Some(index) {
+                        Color::rgb(0.35, 0.6, 0.9)
+                    } else if *count > 0 {
+                        Color::rgb(0.25, 0.25, 0.3)
+                    } else {
+                        Color::rgb(0.15, 0.15, 0.15)
+                    };
+
+                    let on_event = OnEvent::new(
+                        move |In((event_dispatcher_context, _, event, _entity)): In<(
+                            EventDispatcherContext,
+                            WidgetState,
+                            KEvent,
+                            Entity,
+                        )>,
+                        mut select_piece: EventWriter<SelectPieceEvent>| {
+                            if let EventType::Click(..) = event.event_type {
+                                select_piece.send(SelectPieceEvent(index));
+                            }
+                            (event_dispatcher_context, event)
+                        },
+                    );
+
+                    constructor! {
+                        <BackgroundBundle
+                            styles={KStyle {
+                                background_color: StyleProp::Value(color),
+                                width: Units::Stretch(1.0).into(),
+                                height: Units::Pixels(30.0).into(),
+                                cursor: KCursorIcon(CursorIcon::Hand).into(),
+                                ..Default::default()
+                            }}
+                            on_event={on_event}
+                        >
+                            <TextWidgetBundle
+                                text={TextProps {
+                                    alignment: Alignment::Middle,
+                                    content: format!("{} {}", shape_letter(index), count),
+                                    size: 18.0,
+                                    ..Default::default()
+                                }}
+                            />
+                        </BackgroundBundle>
+                    }
+                });
+            }
+        </ElementBundle>
+    };
+
+    true
+}
+
+impl Widget for TrayWidgetProps {}
+
+#[derive(Bundle)]
+pub struct TrayWidgetBundle {
+    props: TrayWidgetProps,
+    styles: KStyle,
+    widget_name: WidgetName,
+}
+
+impl Default for TrayWidgetBundle {
+    fn default() -> Self {
+        Self {
+            props: Default::default(),
+            styles: Default::default(),
+            widget_name: TrayWidgetProps::default().get_name(),
+        }
+    }
+}
+
 // ----- input fields -----
 
 #[derive(Component, Default, Clone, PartialEq)]
@@ -104,6 +465,25 @@ struct TextBoxExample;
 pub struct InputFieldsState {
     pub n: String,
     pub crunch: String,
+    pub beam: String,
+    pub strategy: String,
+    pub weights: String,
+    pub pieces: String,
+    pub counts: String,
+    pub seed: String,
+    pub restarts: String,
+    pub layout: String,
+    /// Pieces shown per second, empty for as fast as they come.
+    pub speed: String,
+    /// `WxH` box to pack into, empty for the smallest rectangle.
+    pub container: String,
+    /// File with a `#` and `.` grid to pack into.
+    pub mask: String,
+}
+
+/// Whether a text box has focus, so letter shortcuts should leave the keys alone.
+pub fn is_typing(text_boxes: &Query<&TextBoxState>) -> bool {
+    text_boxes.iter().any(|text_box| text_box.focused)
 }
 
 impl Widget for TextBoxExample {}
@@ -120,7 +500,7 @@ impl Default for TextBoxExampleBundle {
         Self {
             text_box_example: Default::default(),
             styles: Default::default(),
-            widget_name: TextBoxExample::default().get_name(),
+            widget_name: TextBoxExample.get_name(),
         }
     }
 }
@@ -136,47 +516,428 @@ fn update_input_fields(
         InputFieldsState {
             n: "1".into(),
             crunch: "0".into(),
+            beam: "1".into(),
+            strategy: "greedy".into(),
+            weights: ScoreWeights::default().to_string(),
+            pieces: String::new(),
+            counts: String::new(),
+            seed: String::new(),
+            restarts: String::new(),
+            layout: String::new(),
+            speed: String::new(),
+            container: String::new(),
+            mask: String::new(),
         },
     );
 
-    if let Ok(textbox_state) = state_query.get(state_entity) {
-        let on_change_n = OnChange::new(
-            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
-                  mut state_query: Query<&mut InputFieldsState>| {
-                if let Ok(mut state) = state_query.get_mut(state_entity) {
-                    state.n = value;
-                }
-            },
-        );
+    if let Ok(textbox_state) = state_query.get(state_entity) {
+        let on_change_n = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.n = value;
+                }
+            },
+        );
+
+        let on_change_crunch = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.crunch = value;
+                }
+            },
+        );
+
+        let on_change_beam = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.beam = value;
+                }
+            },
+        );
+
+        let on_change_strategy = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.strategy = value;
+                }
+            },
+        );
+
+        let on_change_weights = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.weights = value;
+                }
+            },
+        );
+
+        let on_change_pieces = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.pieces = value;
+                }
+            },
+        );
+
+        let on_change_counts = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.counts = value;
+                }
+            },
+        );
+
+        let on_change_seed = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.seed = value;
+                }
+            },
+        );
+
+        let on_change_restarts = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.restarts = value;
+                }
+            },
+        );
+
+        let on_change_layout = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.layout = value;
+                }
+            },
+        );
+
+        let on_change_speed = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.speed = value;
+                }
+            },
+        );
+
+        let on_change_container = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.container = value;
+                }
+            },
+        );
+
+        let on_change_mask = OnChange::new(
+            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
+                  mut state_query: Query<&mut InputFieldsState>| {
+                if let Ok(mut state) = state_query.get_mut(state_entity) {
+                    state.mask = value;
+                }
+            },
+        );
+
+        let parent_id = Some(entity);
+        rsx! {
+            <ElementBundle styles={KStyle{
+                layout_type: LayoutType::Grid.into(),
+                height: StyleProp::Value(Units::Pixels(650.)),
+                grid_rows: vec![Units::Stretch(1.0); 13].into(),
+                grid_cols: vec![Units::Stretch(1.0), Units::Stretch(1.0)].into(),
+                ..default()
+            }}>
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 0.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "n =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 0.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(28.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps { value: textbox_state.n.clone(), ..Default::default()}}
+                    on_change={on_change_n}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 1.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Crunch =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 1.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(28.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps { value: textbox_state.crunch.clone(), ..Default::default()}}
+                    on_change={on_change_crunch}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 2.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Beam =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 2.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(28.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps { value: textbox_state.beam.clone(), ..Default::default()}}
+                    on_change={on_change_beam}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 3.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Strategy =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 3.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(28.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps { value: textbox_state.strategy.clone(), ..Default::default()}}
+                    on_change={on_change_strategy}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 4.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Weights =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 4.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(16.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps { value: textbox_state.weights.clone(), ..Default::default()}}
+                    on_change={on_change_weights}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 5.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Pieces =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 5.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(16.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps {
+                        value: textbox_state.pieces.clone(),
+                        placeholder: Some("built-in".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_pieces}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 6.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Counts =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 6.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(16.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps {
+                        value: textbox_state.counts.clone(),
+                        placeholder: Some("n for every piece".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_counts}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 7.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Seed =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 7.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(16.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps {
+                        value: textbox_state.seed.clone(),
+                        placeholder: Some("random".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_seed}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 8.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Restarts =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
 
-        let on_change_crunch = OnChange::new(
-            move |In((_widget_context, _, value)): In<(KayakWidgetContext, Entity, String)>,
-                  mut state_query: Query<&mut InputFieldsState>| {
-                if let Ok(mut state) = state_query.get_mut(state_entity) {
-                    state.crunch = value;
-                }
-            },
-        );
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 8.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(16.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps {
+                        value: textbox_state.restarts.clone(),
+                        placeholder: Some("off, runs or 30s".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_restarts}
+                />
 
-        let parent_id = Some(entity);
-        rsx! {
-            <ElementBundle styles={KStyle{
-                layout_type: LayoutType::Grid.into(),
-                height: StyleProp::Value(Units::Pixels(100.)),
-                grid_rows: vec![Units::Stretch(1.0), Units::Stretch(1.0)].into(),
-                grid_cols: vec![Units::Stretch(1.0), Units::Stretch(1.0)].into(),
-                ..default()
-            }}>
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 9.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Layout =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
+
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 9.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(16.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps {
+                        value: textbox_state.layout.clone(),
+                        placeholder: Some("layout.json".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_layout}
+                />
 
                 <TextWidgetBundle
                     styles={KStyle {
-                        row_index: 0.into(),
+                        row_index: 10.into(),
                         col_index: 0.into(),
                         ..Default::default()
                     }}
                     text={TextProps {
                         alignment: Alignment::Start,
-                        content: "n =".to_string(),
+                        content: "Speed =".to_string(),
                         size: 28.0,
                         ..Default::default()
                     }}
@@ -184,39 +945,75 @@ fn update_input_fields(
 
                 <TextBoxBundle
                     styles={KStyle {
-                        row_index: 0.into(),
+                        row_index: 10.into(),
                         col_index: 1.into(),
-                        font_size: StyleProp::Value(28.),
+                        font_size: StyleProp::Value(16.),
                         ..Default::default()
                     }}
-                    text_box={TextBoxProps { value: textbox_state.n.clone(), ..Default::default()}}
-                    on_change={on_change_n}
+                    text_box={TextBoxProps {
+                        value: textbox_state.speed.clone(),
+                        placeholder: Some("max, pieces/s".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_speed}
                 />
 
                 <TextWidgetBundle
                     styles={KStyle {
-                        row_index: 1.into(),
+                        row_index: 11.into(),
                         col_index: 0.into(),
                         ..Default::default()
                     }}
                     text={TextProps {
                         alignment: Alignment::Start,
-                        content: "Crunch =".to_string(),
+                        content: "Box =".to_string(),
                         size: 28.0,
                         ..Default::default()
                     }}
                 />
 
+                <TextBoxBundle
+                    styles={KStyle {
+                        row_index: 11.into(),
+                        col_index: 1.into(),
+                        font_size: StyleProp::Value(16.),
+                        ..Default::default()
+                    }}
+                    text_box={TextBoxProps {
+                        value: textbox_state.container.clone(),
+                        placeholder: Some("off or WxH".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_container}
+                />
+
+                <TextWidgetBundle
+                    styles={KStyle {
+                        row_index: 12.into(),
+                        col_index: 0.into(),
+                        ..Default::default()
+                    }}
+                    text={TextProps {
+                        alignment: Alignment::Start,
+                        content: "Mask =".to_string(),
+                        size: 28.0,
+                        ..Default::default()
+                    }}
+                />
 
                 <TextBoxBundle
                     styles={KStyle {
-                        row_index: 1.into(),
+                        row_index: 12.into(),
                         col_index: 1.into(),
-                        font_size: StyleProp::Value(28.),
+                        font_size: StyleProp::Value(16.),
                         ..Default::default()
                     }}
-                    text_box={TextBoxProps { value: textbox_state.crunch.clone(), ..Default::default()}}
-                    on_change={on_change_crunch}
+                    text_box={TextBoxProps {
+                        value: textbox_state.mask.clone(),
+                        placeholder: Some("off or file".into()),
+                        ..Default::default()
+                    }}
+                    on_change={on_change_mask}
                 />
             </ElementBundle>
         };
@@ -370,10 +1167,31 @@ fn startup_gui(
     widget_context.add_widget_data::<StateWidgetProps, EmptyState>();
     widget_context.add_widget_system(
         StateWidgetProps::default().get_name(),
-        widget_update_with_resource::<StateWidgetProps, EmptyState>,
+        widget_update_with_resources::<StateWidgetProps, EmptyState>,
         statewidget_render,
     );
 
+    widget_context.add_widget_data::<ProgressWidgetProps, EmptyState>();
+    widget_context.add_widget_system(
+        ProgressWidgetProps::default().get_name(),
+        widget_update_with_resources::<ProgressWidgetProps, EmptyState>,
+        progresswidget_render,
+    );
+
+    widget_context.add_widget_data::<TimelineWidgetProps, EmptyState>();
+    widget_context.add_widget_system(
+        TimelineWidgetProps::default().get_name(),
+        widget_update_with_resources::<TimelineWidgetProps, EmptyState>,
+        timelinewidget_render,
+    );
+
+    widget_context.add_widget_data::<TrayWidgetProps, EmptyState>();
+    widget_context.add_widget_system(
+        TrayWidgetProps::default().get_name(),
+        widget_update_with_resources::<TrayWidgetProps, EmptyState>,
+        traywidget_render,
+    );
+
     let panel1_image = asset_server.load("panel1.png");
     let button_image = asset_server.load("button.png");
     let button_image_hover = asset_server.load("button-hover.png");
@@ -391,11 +1209,188 @@ fn startup_gui(
             Entity,
         )>,
         mut restart: EventWriter<RestartEvent>| {
-            match event.event_type {
-                EventType::Click(..) => {
-                    restart.send(RestartEvent { });
-                }
-                _ => {}
+            if let EventType::Click(..) = event.event_type {
+                restart.send(RestartEvent { mode: RestartMode::Solve });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_manual = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut restart: EventWriter<RestartEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                restart.send(RestartEvent { mode: RestartMode::Manual });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_finish = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut restart: EventWriter<RestartEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                restart.send(RestartEvent { mode: RestartMode::Continue });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_hint = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut hint: EventWriter<HintEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                hint.send(HintEvent { });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_cancel = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut cancel: EventWriter<CancelEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                cancel.send(CancelEvent { });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_save = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut save: EventWriter<SaveLayoutEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                save.send(SaveLayoutEvent { });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_load = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut load: EventWriter<LoadLayoutEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                load.send(LoadLayoutEvent { });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_ascii = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut export_ascii: EventWriter<ExportAsciiEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                export_ascii.send(ExportAsciiEvent { });
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_play = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut playback: EventWriter<PlaybackEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                playback.send(PlaybackEvent::Play);
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_pause = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut playback: EventWriter<PlaybackEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                playback.send(PlaybackEvent::Pause);
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_step = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut playback: EventWriter<PlaybackEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                playback.send(PlaybackEvent::Step);
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_undo = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut history: EventWriter<HistoryEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                history.send(HistoryEvent::Undo);
+            }
+            (event_dispatcher_context, event)
+        },
+    );
+
+    let handle_click_redo = OnEvent::new(
+        move |In((event_dispatcher_context, _, event, _entity)): In<(
+            EventDispatcherContext,
+            WidgetState,
+            KEvent,
+            Entity,
+        )>,
+        mut history: EventWriter<HistoryEvent>| {
+            if let EventType::Click(..) = event.event_type {
+                history.send(HistoryEvent::Redo);
             }
             (event_dispatcher_context, event)
         },
@@ -403,7 +1398,7 @@ fn startup_gui(
 
     widget_context.add_widget_data::<TextBoxExample, InputFieldsState>();
     widget_context.add_widget_system(
-        TextBoxExample::default().get_name(),
+        TextBoxExample.get_name(),
         widget_update::<TextBoxExample, InputFieldsState>,
         update_input_fields,
     );
@@ -419,7 +1414,7 @@ fn startup_gui(
                 }}
                 styles={KStyle {
                     width: Units::Pixels(400.0).into(),
-                    height: Units::Pixels(400.0).into(),
+                    height: Units::Pixels(1520.0).into(),
                     left: Units::Stretch(0.0).into(),
                     right: Units::Stretch(1.0).into(),
                     top: Units::Stretch(1.0).into(),
@@ -439,14 +1434,118 @@ fn startup_gui(
                         area_x: 0,
                         area_y: 0,
                         attempts: 0,
+                        strategy: String::new(),
+                        seed: 0,
+                        runs: 0,
+                        last_area: 0,
+                        best_area: 0,
+                        container: None,
+                        masked: false,
+                        left_over: None,
                     }
                 } />
 
+                <ProgressWidgetBundle />
+
                 <TextBoxExampleBundle />
 
+                <ElementBundle styles={KStyle {
+                    layout_type: LayoutType::Row.into(),
+                    height: Units::Auto.into(),
+                    ..Default::default()
+                }}>
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Restart".into() }}
+                        on_event={handle_click_close}
+                    />
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Cancel".into() }}
+                        on_event={handle_click_cancel}
+                    />
+                </ElementBundle>
+
+                <ElementBundle styles={KStyle {
+                    layout_type: LayoutType::Row.into(),
+                    height: Units::Auto.into(),
+                    ..Default::default()
+                }}>
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Manual".into() }}
+                        on_event={handle_click_manual}
+                    />
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Finish".into() }}
+                        on_event={handle_click_finish}
+                    />
+                </ElementBundle>
+
+                <TrayWidgetBundle />
+
+                <ElementBundle styles={KStyle {
+                    layout_type: LayoutType::Row.into(),
+                    height: Units::Auto.into(),
+                    ..Default::default()
+                }}>
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Play".into() }}
+                        on_event={handle_click_play}
+                    />
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Pause".into() }}
+                        on_event={handle_click_pause}
+                    />
+                </ElementBundle>
+
+                <ElementBundle styles={KStyle {
+                    layout_type: LayoutType::Row.into(),
+                    height: Units::Auto.into(),
+                    ..Default::default()
+                }}>
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Step".into() }}
+                        on_event={handle_click_step}
+                    />
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Hint".into() }}
+                        on_event={handle_click_hint}
+                    />
+                </ElementBundle>
+
+                <TimelineWidgetBundle />
+
+                <ElementBundle styles={KStyle {
+                    layout_type: LayoutType::Row.into(),
+                    height: Units::Auto.into(),
+                    ..Default::default()
+                }}>
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Undo".into() }}
+                        on_event={handle_click_undo}
+                    />
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Redo".into() }}
+                        on_event={handle_click_redo}
+                    />
+                </ElementBundle>
+
+                <ElementBundle styles={KStyle {
+                    layout_type: LayoutType::Row.into(),
+                    height: Units::Auto.into(),
+                    ..Default::default()
+                }}>
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Save".into() }}
+                        on_event={handle_click_save}
+                    />
+                    <MenuButtonBundle
+                        button={MenuButton { text: "Load".into() }}
+                        on_event={handle_click_load}
+                    />
+                </ElementBundle>
+
                 <MenuButtonBundle
-                    button={MenuButton { text: "Restart".into() }}
-                    on_event={handle_click_close}
+                    button={MenuButton { text: "ASCII".into() }}
+                    on_event={handle_click_ascii}
                 />
 
             </NinePatchBundle>
@@ -2,9 +2,20 @@ use bevy::{prelude::*, window::PresentMode};
 use kluring::KluringPlugin;
 // use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+mod cli;
 mod kluring;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if cli::is_headless(&args) {
+        if let Err(message) = cli::run(&args) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
 
     .add_plugins(DefaultPlugins.set(WindowPlugin {
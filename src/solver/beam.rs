@@ -0,0 +1,203 @@
+//! Beam search: instead of committing to the single best placement, keep the
+//! `beam_width` best partial boards after every step.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{
+    find_candidates, place, BoardState, Candidate, GreedyStrategy, Placement,
+    PlacementStrategy, ShapeBag, ShapePermutation, INITIAL,
+};
+
+#[derive(Clone)]
+struct BeamState {
+    board: BoardState,
+    bag: ShapeBag,
+    placements: Vec<Placement>,
+    /// Sum of the placement scores along the way.
+    score: i32,
+}
+
+impl BeamState {
+    fn rank(&self) -> i32 {
+        self.score - self.board.bounds.area()
+    }
+}
+
+/// Every available shape permutation at `INITIAL`, since an empty board has no border to score.
+pub(super) fn first_candidates(bag: &ShapeBag) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+    for shape in bag.iter_available() {
+        for orientation in &shape.orientations {
+            candidates.push(Candidate {
+                score: 0,
+                placement: Placement {
+                    permutation: ShapePermutation {
+                        index: shape.index,
+                        permutation: *orientation,
+                    },
+                    pos: INITIAL,
+                },
+            });
+        }
+    }
+    candidates
+}
+
+/// Runs beam search from the given board until the bag is empty, or until
+/// `cancel` is set. Returns the placements of the smallest finished board and
+/// the number of attempts spent over all boards.
+pub fn beam_search(
+    board: &BoardState,
+    bag: &ShapeBag,
+    beam_width: usize,
+    cancel: &AtomicBool,
+) -> (Vec<Placement>, usize) {
+    let (mut best, mut attempts) = search(board, bag, beam_width, cancel);
+
+    // truncating can drop the width 1 line, so keep it as a floor
+    if beam_width > 1 {
+        let (narrow, narrow_attempts) = search(board, bag, 1, cancel);
+        attempts += narrow_attempts;
+        best = best.into_iter().chain(narrow).min_by_key(smallest);
+    }
+
+    match best {
+        Some(state) => (state.placements, attempts),
+        None => (Vec::new(), attempts),
+    }
+}
+
+fn smallest(state: &BeamState) -> (i32, std::cmp::Reverse<i32>) {
+    (state.board.bounds.area(), std::cmp::Reverse(state.score))
+}
+
+fn search(
+    board: &BoardState,
+    bag: &ShapeBag,
+    beam_width: usize,
+    cancel: &AtomicBool,
+) -> (Option<BeamState>, usize) {
+    let beam_width = beam_width.max(1);
+
+    let mut attempts = 0;
+    let mut beam = vec![BeamState {
+        board: board.clone(),
+        bag: bag.clone(),
+        placements: Vec::new(),
+        score: 0,
+    }];
+
+    while beam.iter().any(|state| !state.bag.is_empty()) {
+        let mut next_beam = Vec::new();
+
+        for mut state in std::mem::take(&mut beam) {
+            // wide beams take a while per step, so check on every board
+            if cancel.load(Ordering::Relaxed) {
+                return (None, attempts);
+            }
+
+            if state.bag.is_empty() {
+                next_beam.push(state);
+                continue;
+            }
+
+            let mut candidates = if state.board.is_empty() {
+                first_candidates(&state.bag)
+            } else {
+                let before = state.board.attempts;
+                let candidates = find_candidates(&mut state.board, &state.bag);
+                attempts += state.board.attempts - before;
+                candidates
+            };
+
+            // best first, ties keep enumeration order
+            candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.score));
+            candidates.truncate(beam_width);
+
+            for candidate in candidates {
+                let mut next = state.clone();
+                place(&mut next.board, &mut next.bag, &candidate.placement);
+                next.placements.push(candidate.placement);
+                next.score += candidate.score;
+                next_beam.push(next);
+            }
+        }
+
+        if next_beam.is_empty() {
+            break;
+        }
+
+        next_beam.sort_by_key(|state| std::cmp::Reverse(state.rank()));
+        next_beam.truncate(beam_width);
+        beam = next_beam;
+    }
+
+    (beam.into_iter().min_by_key(smallest), attempts)
+}
+
+/// Plans the whole board with `beam_search` on the first step, then replays it.
+pub struct BeamStrategy {
+    beam_width: usize,
+    planned: Option<VecDeque<Placement>>,
+}
+
+impl BeamStrategy {
+    pub fn new(beam_width: usize) -> BeamStrategy {
+        BeamStrategy {
+            beam_width,
+            planned: None,
+        }
+    }
+}
+
+impl PlacementStrategy for BeamStrategy {
+    fn name(&self) -> &'static str {
+        "beam"
+    }
+
+    fn next_placement(&mut self, board: &mut BoardState, bag: &ShapeBag) -> Option<Placement> {
+        if self.planned.is_none() {
+            let (plan, attempts) = beam_search(board, bag, self.beam_width, &board.cancel);
+            board.attempts += attempts;
+            self.planned = Some(plan.into());
+        }
+
+        match self.planned.as_mut().and_then(|planned| planned.pop_front()) {
+            Some(placement) => Some(placement),
+            // beam came up short, finish greedily
+            None => GreedyStrategy.next_placement(board, bag),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.planned = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beam_area(beam_width: usize) -> i32 {
+        let mut board = BoardState::new(0);
+        let mut bag = ShapeBag::load(1);
+        let (plan, _) = beam_search(&board, &bag, beam_width, &AtomicBool::default());
+        for placement in &plan {
+            place(&mut board, &mut bag, placement);
+        }
+
+        assert!(bag.is_empty());
+        board.bounds.area()
+    }
+
+    #[test]
+    fn wider_beam_is_never_worse() {
+        let narrow = beam_area(1);
+        for beam_width in [2, 4, 6] {
+            assert!(beam_area(beam_width) <= narrow, "width {}", beam_width);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Dense occupancy bits so overlap tests are a handful of ANDs instead of a
+//! hash lookup per tile.
+
+use super::{Bounds, GlobalPos};
+
+/// Extra room added on every side when the board has to grow.
+const GROW_MARGIN: i32 = 32;
+
+/// Tiles of one shape permutation, with each row packed into a word.
+#[derive(Clone)]
+pub struct ShapeMask {
+    pub tiles: Vec<GlobalPos>,
+    /// Corners of the tiles' bounding box in shape coordinates.
+    pub min: GlobalPos,
+    pub max: GlobalPos,
+    /// Bit `k` of row `r` is the tile at `min + (k, r)`.
+    pub rows: Vec<u64>,
+}
+
+impl ShapeMask {
+    pub const MAX_WIDTH: i32 = 64;
+
+    pub fn new(tiles: Vec<GlobalPos>) -> ShapeMask {
+        let min = GlobalPos {
+            x: tiles.iter().map(|pos| pos.x).min().unwrap_or(0),
+            y: tiles.iter().map(|pos| pos.y).min().unwrap_or(0),
+        };
+        let max = GlobalPos {
+            x: tiles.iter().map(|pos| pos.x).max().unwrap_or(0),
+            y: tiles.iter().map(|pos| pos.y).max().unwrap_or(0),
+        };
+
+        assert!(max.x - min.x < Self::MAX_WIDTH, "Shapes can be at most {} tiles wide.", Self::MAX_WIDTH);
+
+        let mut rows = vec![0; (max.y - min.y + 1) as usize];
+        for tile in &tiles {
+            rows[(tile.y - min.y) as usize] |= 1 << (tile.x - min.x);
+        }
+
+        ShapeMask {
+            tiles,
+            min,
+            max,
+            rows,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Bitboard {
+    /// Global position of the first bit of the first row.
+    origin: GlobalPos,
+    words_per_row: usize,
+    height: usize,
+    words: Vec<u64>,
+}
+
+impl Bitboard {
+    pub fn new() -> Bitboard {
+        Bitboard::default()
+    }
+
+    fn word(&self, row: i32, word: i32) -> u64 {
+        if row < 0 || row >= self.height as i32 || word < 0 || word >= self.words_per_row as i32 {
+            return 0;
+        }
+        self.words[row as usize * self.words_per_row + word as usize]
+    }
+
+    pub fn get(&self, pos: &GlobalPos) -> bool {
+        let column = pos.x - self.origin.x;
+        let bits = self.word(pos.y - self.origin.y, column.div_euclid(64));
+        bits & (1 << column.rem_euclid(64)) != 0
+    }
+
+    pub fn set(&mut self, pos: &GlobalPos) {
+        if !self.covers(pos) {
+            self.grow_to(pos);
+        }
+
+        let column = (pos.x - self.origin.x) as usize;
+        let row = (pos.y - self.origin.y) as usize;
+        self.words[row * self.words_per_row + column / 64] |= 1 << (column % 64);
+    }
+
+    /// 64 cells of row `y`, starting at column `x`.
+    fn row_bits(&self, x: i32, y: i32) -> u64 {
+        let row = y - self.origin.y;
+        let column = x - self.origin.x;
+        let word = column.div_euclid(64);
+        let shift = column.rem_euclid(64);
+
+        let low = self.word(row, word) >> shift;
+        if shift == 0 {
+            return low;
+        }
+        low | self.word(row, word + 1) << (64 - shift)
+    }
+
+    /// Whether the mask moved by `offset` hits any set cell.
+    pub fn intersects(&self, mask: &ShapeMask, offset: GlobalPos) -> bool {
+        let x = offset.x + mask.min.x;
+        let y = offset.y + mask.min.y;
+
+        mask.rows
+            .iter()
+            .enumerate()
+            .any(|(row, bits)| self.row_bits(x, y + row as i32) & bits != 0)
+    }
+
+    fn covers(&self, pos: &GlobalPos) -> bool {
+        pos.x >= self.origin.x
+            && pos.y >= self.origin.y
+            && pos.x < self.origin.x + self.words_per_row as i32 * 64
+            && pos.y < self.origin.y + self.height as i32
+    }
+
+    /// Reallocates so the covered area plus a margin around `pos` fit, keeping set cells.
+    fn grow_to(&mut self, pos: &GlobalPos) {
+        let margin = GlobalPos { x: GROW_MARGIN, y: GROW_MARGIN };
+
+        let mut bounds = Bounds::new();
+        if !self.words.is_empty() {
+            bounds.expand(&self.origin);
+            bounds.expand(&GlobalPos {
+                x: self.origin.x + self.words_per_row as i32 * 64 - 1,
+                y: self.origin.y + self.height as i32 - 1,
+            });
+        }
+        bounds.expand(&(*pos - margin));
+        bounds.expand(&(*pos + margin));
+
+        let mut grown = Bitboard {
+            origin: GlobalPos { x: bounds.min_x, y: bounds.min_y },
+            words_per_row: (bounds.width() as usize).div_ceil(64),
+            height: bounds.height() as usize,
+            words: Vec::new(),
+        };
+        grown.words = vec![0; grown.words_per_row * grown.height];
+
+        for row in 0..self.height {
+            for word in 0..self.words_per_row {
+                let bits = self.words[row * self.words_per_row + word];
+                for bit in 0..64 {
+                    if bits & (1 << bit) != 0 {
+                        grown.set(&GlobalPos {
+                            x: self.origin.x + (word * 64 + bit) as i32,
+                            y: self.origin.y + row as i32,
+                        });
+                    }
+                }
+            }
+        }
+
+        *self = grown;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    fn pos(x: i32, y: i32) -> GlobalPos {
+        GlobalPos { x, y }
+    }
+
+    #[test]
+    fn empty_board_has_nothing_set() {
+        let board = Bitboard::new();
+        assert!(!board.get(&pos(0, 0)));
+        assert!(!board.intersects(&ShapeMask::new(vec![pos(0, 0)]), pos(-5, 7)));
+    }
+
+    #[test]
+    fn growing_keeps_set_cells() {
+        let mut board = Bitboard::new();
+        board.set(&pos(0, 0));
+        board.set(&pos(-3, 2));
+
+        // far enough to reallocate in both directions
+        board.set(&pos(500, -400));
+        board.set(&pos(-700, 300));
+
+        for set in [pos(0, 0), pos(-3, 2), pos(500, -400), pos(-700, 300)] {
+            assert!(board.get(&set), "Lost {:?}", set);
+        }
+        assert!(!board.get(&pos(1, 0)));
+        assert!(!board.get(&pos(-3, 3)));
+    }
+
+    #[test]
+    fn intersects_across_word_boundaries() {
+        let mut board = Bitboard::new();
+        board.set(&pos(64, 0));
+
+        let bar = ShapeMask::new(vec![pos(0, 0), pos(1, 0), pos(2, 0)]);
+        assert!(board.intersects(&bar, pos(62, 0)));
+        assert!(board.intersects(&bar, pos(64, 0)));
+        assert!(!board.intersects(&bar, pos(61, 0)));
+        assert!(!board.intersects(&bar, pos(65, 0)));
+        assert!(!board.intersects(&bar, pos(62, 1)));
+    }
+
+    #[test]
+    fn intersects_matches_a_hash_set() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut board = Bitboard::new();
+        let mut set = HashSet::new();
+
+        for _ in 0..300 {
+            let cell = pos(rng.gen_range(-80..80), rng.gen_range(-40..40));
+            board.set(&cell);
+            set.insert(cell);
+        }
+
+        let l_shape = ShapeMask::new(vec![pos(0, 0), pos(0, 1), pos(0, 2), pos(1, 2)]);
+        for _ in 0..2000 {
+            let offset = pos(rng.gen_range(-90..90), rng.gen_range(-50..50));
+            let expected = l_shape.tiles.iter().any(|tile| set.contains(&(*tile + offset)));
+            assert_eq!(board.intersects(&l_shape, offset), expected, "At {:?}", offset);
+        }
+    }
+}
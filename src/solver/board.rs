@@ -0,0 +1,519 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bitboard::{Bitboard, ShapeMask},
+    pos::{GlobalPos, iter_moore},
+    score::ScoreWeights,
+};
+
+pub const INITIAL: GlobalPos = GlobalPos { x: 0, y: 0 };
+
+pub const BLOCKED: i32 = i32::MIN;
+
+/// Empty position next to at least one placed tile.
+#[derive(Clone, Default)]
+pub struct Border {
+    pub adjacency_score: i32,
+    pub distance_score: i32,
+    pub dead: bool,
+}
+
+impl Border {
+    pub fn score(&self, weights: &ScoreWeights) -> i32 {
+        weights.adjacency * self.adjacency_score + weights.distance * self.distance_score
+    }
+}
+
+#[derive(Clone)]
+pub struct BoardState {
+    /// Placed tiles are `BLOCKED`, border positions hold their current score.
+    pub scored_positions: HashMap<GlobalPos, i32>,
+    /// Filled tiles again, packed for fast overlap tests.
+    pub occupied: Bitboard,
+    pub border: HashMap<GlobalPos, Border>,
+    pub bounds: Bounds,
+    pub attempts: usize,
+    pub max_attempts: usize,
+    pub weights: ScoreWeights,
+    pub seed: u64,
+    /// Drawn from for anything random, starts over from `seed` on every reset.
+    pub rng: StdRng,
+    /// Tiles blocked before anything was placed, like the walls of `container`.
+    pub obstacles: Vec<GlobalPos>,
+    /// Box every placed tile has to stay inside, if any.
+    pub container: Option<Bounds>,
+    /// Whether `container` is the bounding box of a mask rather than a box of its own.
+    pub masked: bool,
+    /// Set from another thread to stop the solve, searches check it as they go.
+    pub cancel: Arc<AtomicBool>,
+    /// Summed coordinates and count of the placed tiles, for their center of mass.
+    tile_sum: (i64, i64),
+    tile_count: i64,
+}
+
+impl BoardState {
+    /// Board with a fresh random seed.
+    pub fn new(max_attempts: usize) -> BoardState {
+        let seed = rand::random();
+        BoardState {
+            scored_positions: HashMap::new(),
+            occupied: Bitboard::new(),
+            border: HashMap::new(),
+            bounds: Bounds::new(),
+            attempts: 0,
+            max_attempts,
+            weights: ScoreWeights::default(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            obstacles: Vec::new(),
+            container: None,
+            masked: false,
+            cancel: Arc::default(),
+            tile_sum: (0, 0),
+            tile_count: 0,
+        }
+    }
+
+    /// Clears the board but keeps the score weights, seed, container,
+    /// obstacles and cancel flag, so the run replays.
+    pub fn reset(&mut self, max_attempts: usize) {
+        let weights = std::mem::take(&mut self.weights);
+        let obstacles = std::mem::take(&mut self.obstacles);
+        let container = self.container.take();
+        let masked = self.masked;
+        let cancel = self.cancel.clone();
+        let seed = self.seed;
+        *self = BoardState::new(max_attempts);
+        self.weights = weights;
+        self.set_seed(seed);
+        self.container = container;
+        self.masked = masked;
+        self.cancel = cancel;
+        self.block(&obstacles);
+    }
+
+    /// Confines the board to `width` × `height` tiles from `INITIAL` by walling
+    /// the rectangle in. Meant for an empty board.
+    pub fn set_container(&mut self, width: i32, height: i32) {
+        let container = Bounds {
+            min_x: INITIAL.x,
+            min_y: INITIAL.y,
+            max_x: INITIAL.x + width - 1,
+            max_y: INITIAL.y + height - 1,
+        };
+
+        let mut walls = Vec::new();
+        for x in container.min_x - 1..=container.max_x + 1 {
+            walls.push(GlobalPos { x, y: container.min_y - 1 });
+            walls.push(GlobalPos { x, y: container.max_y + 1 });
+        }
+        for y in container.min_y..=container.max_y {
+            walls.push(GlobalPos { x: container.min_x - 1, y });
+            walls.push(GlobalPos { x: container.max_x + 1, y });
+        }
+
+        self.container = Some(container);
+        self.block(&walls);
+    }
+
+    /// Confines the board to the open cells of `mask`, walled in like `set_container`.
+    /// Meant for an empty board.
+    pub fn set_mask(&mut self, mask: &BoardMask) {
+        self.set_container(mask.width, mask.height);
+        self.masked = true;
+
+        let blocked: Vec<GlobalPos> = mask.blocked.iter().map(|pos| INITIAL + *pos).collect();
+        self.block(&blocked);
+    }
+
+    /// Blocks tiles without placing anything there, and opens the border next
+    /// to them so shapes can be anchored against them.
+    pub fn block(&mut self, positions: &[GlobalPos]) {
+        for global_pos in positions {
+            self.scored_positions.insert(*global_pos, BLOCKED);
+            self.occupied.set(global_pos);
+            self.border.remove(global_pos);
+            self.obstacles.push(*global_pos);
+        }
+
+        for global_pos in positions {
+            for neighbor_pos in iter_moore(*global_pos) {
+                if !self.scored_positions.contains_key(&neighbor_pos) && self.is_inside(&neighbor_pos) {
+                    self.border.entry(neighbor_pos).or_default();
+                    self.scored_positions.insert(neighbor_pos, 0);
+                }
+            }
+        }
+
+        self.update_boundary_score();
+    }
+
+    /// Whether the position is inside the container, always true without one.
+    pub fn is_inside(&self, pos: &GlobalPos) -> bool {
+        self.container.as_ref().is_none_or(|container| container.contains(pos))
+    }
+
+    /// Whether the mask moved by `offset` stays inside the container and off any blocked tile.
+    pub fn fits(&self, mask: &ShapeMask, offset: GlobalPos) -> bool {
+        self.is_inside(&(mask.min + offset))
+            && self.is_inside(&(mask.max + offset))
+            && !self.occupied.intersects(mask, offset)
+    }
+
+    /// Where pocket flood fills may look: walls enclose the whole container.
+    pub fn pocket_bounds<'a>(&'a self, bounds: &'a Bounds) -> &'a Bounds {
+        self.container.as_ref().unwrap_or(bounds)
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn is_taken(&self, pos: &GlobalPos) -> bool {
+        self.scored_positions.get(pos).is_none_or(|x| *x == BLOCKED)
+    }
+
+    /// Whether anything fills the tile. Unlike `is_taken`, false for empty
+    /// cells the border hasn't reached yet.
+    pub fn is_blocked(&self, pos: &GlobalPos) -> bool {
+        self.occupied.get(pos)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scored_positions.is_empty()
+    }
+
+    /// Marks the given tiles as blocked and rescores the border around them.
+    pub fn place(&mut self, positions: &[GlobalPos]) {
+
+        for global_pos in positions {
+
+            if let Some(prev) = self.scored_positions.insert(*global_pos, BLOCKED) {
+                if prev == BLOCKED {
+                    panic!("Overlapped old tile!");
+                }
+            }
+
+            self.occupied.set(global_pos);
+            self.border.remove(global_pos);
+            self.bounds.expand(global_pos);
+
+            self.tile_sum.0 += global_pos.x as i64;
+            self.tile_sum.1 += global_pos.y as i64;
+            self.tile_count += 1;
+        }
+
+        for global_pos in positions {
+            for neighbor_pos in iter_moore(*global_pos) {
+                if !self.scored_positions.contains_key(&neighbor_pos) {
+                    // known before rescoring, so adjacency doesn't depend on map order
+                    self.border.entry(neighbor_pos).or_default();
+                    self.scored_positions.insert(neighbor_pos, 0);
+                }
+            }
+        }
+
+        self.update_boundary_score();
+    }
+
+    /// Average position of the placed tiles, `INITIAL` before any are placed.
+    pub fn center_of_mass(&self) -> (f32, f32) {
+        if self.tile_count == 0 {
+            return (INITIAL.x as f32, INITIAL.y as f32);
+        }
+
+        let count = self.tile_count as f32;
+        (self.tile_sum.0 as f32 / count, self.tile_sum.1 as f32 / count)
+    }
+
+    fn update_boundary_score(&mut self) {
+
+        let center_of_mass = self.center_of_mass();
+
+        if self.bounds.is_default() {
+            return;
+        }
+
+        let max_distance = ((self.bounds.width().pow(2) + self.bounds.height().pow(2)) as f32).sqrt();
+
+        let mut border = std::mem::take(&mut self.border);
+
+        for (global_pos, border) in border.iter_mut() {
+
+            border.adjacency_score = 0;
+            for neighbor in iter_moore(*global_pos) {
+                if self.is_taken(&neighbor) {
+                    border.adjacency_score += 1;
+                }
+            }
+
+            let distance_x = global_pos.x as f32 - center_of_mass.0;
+            let distance_y = global_pos.y as f32 - center_of_mass.1;
+            let distance = (distance_x.powi(2) + distance_y.powi(2)).sqrt();
+
+            let normalized_distance = (max_distance - distance) / max_distance;
+            border.distance_score = (normalized_distance * 10.) as i32;
+        }
+
+        // store scores only once all are known, so new border cells next to
+        // each other don't see one another depending on hash map order
+        for (global_pos, border) in border.iter() {
+            if let Some(prev) = self.scored_positions.insert(*global_pos, border.score(&self.weights)) {
+                if prev == BLOCKED {
+                    panic!("Overwrote blocked position at {}, {}", global_pos.x, global_pos.y);
+                }
+            }
+        }
+
+        self.border = border;
+    }
+
+    /// Live border positions to try anchoring shapes on, best first if `sort_best`.
+    pub fn collect_candidate_positions(&self, sort_best: bool) -> Vec<GlobalPos> {
+
+        let mut border_tiles: Vec<(&GlobalPos, &Border)> = self.border
+            .iter()
+            .filter(|(_, border)| !border.dead)
+            .collect();
+
+        // keep runs independent of hash map order
+        border_tiles.sort_by_key(|(global_pos, _)| **global_pos);
+
+        if sort_best {
+            border_tiles.sort_by_key(|(_, border)| std::cmp::Reverse(border.score(&self.weights)));
+        }
+
+        let mut border: Vec<GlobalPos> = border_tiles
+            .iter()
+            .map(|(global_pos, _)| **global_pos)
+            .collect();
+
+        if border.is_empty() {
+
+            // start at center...
+            border.push(INITIAL);
+        }
+
+        border
+    }
+}
+
+/// Size of a box to pack into, written `WxH` like `10x6`.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoxSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl FromStr for BoxSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid box {}, expected WxH like 10x6", s.trim());
+
+        let (width, height) = s.trim().split_once(['x', 'X', '*']).ok_or_else(invalid)?;
+        let width = width.trim().parse::<i32>().map_err(|_| invalid())?;
+        let height = height.trim().parse::<i32>().map_err(|_| invalid())?;
+
+        if width < 1 || height < 1 {
+            return Err(invalid());
+        }
+        Ok(BoxSize { width, height })
+    }
+}
+
+/// Irregular region to pack into, drawn like a piece: `#` is blocked and `.`
+/// is open, rows going down with y. Short rows are blocked past their end.
+#[derive(Clone)]
+pub struct BoardMask {
+    pub width: i32,
+    pub height: i32,
+    /// Blocked cells, counted from the top left.
+    pub blocked: Vec<GlobalPos>,
+}
+
+impl BoardMask {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<BoardMask, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        BoardMask::parse(&text)
+    }
+
+    /// Lines starting with `//` are comments, blank lines around the grid are ignored.
+    pub fn parse(text: &str) -> Result<BoardMask, String> {
+        let lines: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim_start().starts_with("//"))
+            .map(|(line_index, line)| (line_index, line.trim_end()))
+            .skip_while(|(_, line)| line.is_empty())
+            .collect();
+        let lines = match lines.iter().rposition(|(_, line)| !line.is_empty()) {
+            Some(last) => &lines[..=last],
+            None => return Err("Mask has no rows".into()),
+        };
+
+        let width = lines.iter().map(|(_, line)| line.chars().count()).max().unwrap_or(0) as i32;
+        let mut blocked = Vec::new();
+
+        for (y, (line_index, line)) in lines.iter().enumerate() {
+            let mut row: Vec<char> = line.chars().collect();
+            row.resize(width as usize, '#');
+
+            for (x, char) in row.into_iter().enumerate() {
+                match char {
+                    '.' => {},
+                    '#' => blocked.push(GlobalPos { x: x as i32, y: y as i32 }),
+                    _ => return Err(format!("Line {}: unexpected {:?} in mask, use # and .", line_index + 1, char)),
+                }
+            }
+        }
+
+        let mask = BoardMask { width, height: lines.len() as i32, blocked };
+        if mask.open_count() == 0 {
+            return Err("Mask has no open cells".into());
+        }
+        Ok(mask)
+    }
+
+    pub fn open_count(&self) -> i32 {
+        self.width * self.height - self.blocked.len() as i32
+    }
+}
+
+#[derive(Clone)]
+pub struct Bounds {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub max_x: i32,
+    pub max_y: i32,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds::new()
+    }
+}
+
+impl Bounds {
+    pub fn new() -> Bounds {
+        Bounds {
+            min_x: i32::MAX,
+            max_x: i32::MIN,
+            min_y: i32::MAX,
+            max_y: i32::MIN,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.min_x == i32::MAX &&
+        self.max_x == i32::MIN &&
+        self.min_y == i32::MAX &&
+        self.max_y == i32::MIN
+
+    }
+
+    pub fn width(&self) -> i32 {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.max_y - self.min_y + 1
+    }
+
+    pub fn area(&self) -> i32 {
+        if self.is_default() {
+            return 0;
+        }
+        self.width() * self.height()
+    }
+
+    pub fn contains(&self, global_pos: &GlobalPos) -> bool {
+        global_pos.x >= self.min_x && global_pos.x <= self.max_x &&
+        global_pos.y >= self.min_y && global_pos.y <= self.max_y
+    }
+
+    pub fn expand(&mut self, global_pos: &GlobalPos) {
+
+        self.max_x = global_pos.x.max(self.max_x);
+        self.max_y = global_pos.y.max(self.max_y);
+        self.min_x = global_pos.x.min(self.min_x);
+        self.min_y = global_pos.y.min(self.min_y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: i32, y: i32) -> GlobalPos {
+        GlobalPos { x, y }
+    }
+
+    #[test]
+    fn mask_parse_reads_blocked_cells() {
+        let mask = BoardMask::parse("#..\n..#\n").unwrap();
+
+        assert_eq!((mask.width, mask.height), (3, 2));
+        assert_eq!(mask.blocked, vec![pos(0, 0), pos(2, 1)]);
+        assert_eq!(mask.open_count(), 4);
+    }
+
+    #[test]
+    fn mask_parse_skips_comments_and_blank_edges() {
+        let mask = BoardMask::parse("// a ring\n\n###\n#.#\n  // between rows\n###\n\n\n").unwrap();
+
+        assert_eq!((mask.width, mask.height), (3, 3));
+        assert_eq!(mask.open_count(), 1);
+        assert!(!mask.blocked.contains(&pos(1, 1)));
+    }
+
+    #[test]
+    fn mask_parse_blocks_short_rows_past_their_end() {
+        let mask = BoardMask::parse("....\n..\n...   \n").unwrap();
+
+        assert_eq!((mask.width, mask.height), (4, 3));
+        assert_eq!(mask.blocked, vec![pos(2, 1), pos(3, 1), pos(3, 2)]);
+    }
+
+    #[test]
+    fn mask_parse_rejects_bad_grids() {
+        let message = BoardMask::parse("..\n.x\n").err().unwrap();
+        assert!(message.starts_with("Line 2"), "{}", message);
+
+        assert!(BoardMask::parse("// only a comment\n").is_err());
+        assert!(BoardMask::parse("##\n##\n").is_err());
+    }
+
+    #[test]
+    fn set_mask_keeps_shapes_on_open_cells() {
+        let mut board = BoardState::new(0);
+        board.set_mask(&BoardMask::parse("...\n.#.\n").unwrap());
+        let domino = ShapeMask::new(vec![pos(0, 0), pos(1, 0)]);
+
+        assert!(board.fits(&domino, pos(0, 0)));
+        assert!(!board.fits(&domino, pos(0, 1)));
+        assert!(!board.fits(&domino, pos(2, 0)));
+        assert!(!board.fits(&domino, pos(0, -1)));
+
+        board.reset(0);
+        assert!(board.masked);
+    }
+}
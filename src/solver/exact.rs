@@ -0,0 +1,512 @@
+//! Exact packing: decide whether the whole bag fits a W×H rectangle, and
+//! search rectangles by increasing area to find the true minimum.
+//!
+//! This is Algorithm X with piece multiplicities: every shape in the bag is a
+//! primary column that must be covered `remaining` times, every cell of the
+//! rectangle is a secondary column that may be covered at most once.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use super::{
+    BoardState, GlobalPos, GreedyStrategy, Permutation, Placement, PlacementStrategy, ShapeBag,
+    ShapePermutation, INITIAL,
+};
+
+#[derive(Clone)]
+struct CellSet {
+    words: Vec<u64>,
+}
+
+impl CellSet {
+    fn new(len: usize) -> CellSet {
+        CellSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    fn intersects(&self, other: &CellSet) -> bool {
+        self.words.iter().zip(&other.words).any(|(a, b)| a & b != 0)
+    }
+
+    fn union_with(&mut self, other: &CellSet) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
+        }
+    }
+
+    /// Adds the cells of `other` if absent, removes them if present.
+    fn toggle(&mut self, other: &CellSet) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a ^= b;
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+}
+
+/// One way of putting one shape inside the rectangle.
+struct Row {
+    placement: Placement,
+    cells: CellSet,
+}
+
+/// Orientations of a shape as (permutation, offset to normalize, normalized tiles).
+fn orientations(bag: &ShapeBag, shape_index: usize) -> Vec<(Permutation, GlobalPos, Vec<GlobalPos>)> {
+    let shape = bag.shape(shape_index);
+
+    shape.orientations
+        .iter()
+        .map(|permutation| {
+            let tiles = shape.iter_pos(permutation);
+
+            let min = GlobalPos {
+                x: tiles.iter().map(|pos| pos.x).min().unwrap_or(0),
+                y: tiles.iter().map(|pos| pos.y).min().unwrap_or(0),
+            };
+
+            let normalized = tiles.iter().map(|pos| *pos - min).collect();
+            (*permutation, min, normalized)
+        })
+        .collect()
+}
+
+pub struct ExactPacker {
+    width: i32,
+    height: i32,
+    rows_per_shape: Vec<Vec<Row>>,
+    remaining: Vec<u16>,
+    cell_count: usize,
+    /// Cells no shape may cover, already occupied when the search starts.
+    blocked: CellSet,
+    tile_count: usize,
+    /// Cells that have to stay empty once everything is placed.
+    slack: usize,
+    /// Search nodes visited so far.
+    pub nodes: usize,
+    /// Gives up on the search once set.
+    cancel: Arc<AtomicBool>,
+}
+
+impl ExactPacker {
+    pub fn new(bag: &ShapeBag, width: i32, height: i32) -> ExactPacker {
+        let cell_count = (width.max(0) * height.max(0)) as usize;
+        let mut rows_per_shape = Vec::new();
+        let mut remaining = Vec::new();
+        let mut tile_count = 0;
+
+        for shape_index in 0..bag.shape_count() {
+            let mut rows = Vec::new();
+
+            if bag.remaining(shape_index) > 0 {
+                let shape_orientations = orientations(bag, shape_index);
+                let shape_tiles = shape_orientations.first().map_or(0, |(_, _, tiles)| tiles.len());
+                tile_count += shape_tiles * bag.remaining(shape_index) as usize;
+
+                for (permutation, min, tiles) in shape_orientations {
+                    let shape_width = tiles.iter().map(|pos| pos.x).max().unwrap_or(0) + 1;
+                    let shape_height = tiles.iter().map(|pos| pos.y).max().unwrap_or(0) + 1;
+
+                    for y in 0..=(height - shape_height) {
+                        for x in 0..=(width - shape_width) {
+                            let mut cells = CellSet::new(cell_count);
+                            for tile in &tiles {
+                                cells.insert(((tile.y + y) * width + tile.x + x) as usize);
+                            }
+
+                            rows.push(Row {
+                                placement: Placement {
+                                    permutation: ShapePermutation { index: shape_index, permutation },
+                                    pos: INITIAL + GlobalPos { x, y } - min,
+                                },
+                                cells,
+                            });
+                        }
+                    }
+                }
+            }
+
+            rows_per_shape.push(rows);
+            remaining.push(bag.remaining(shape_index));
+        }
+
+        ExactPacker {
+            width,
+            height,
+            rows_per_shape,
+            remaining,
+            cell_count,
+            blocked: CellSet::new(cell_count),
+            tile_count,
+            slack: cell_count.saturating_sub(tile_count),
+            nodes: 0,
+            cancel: Arc::default(),
+        }
+    }
+
+    /// Lets another thread stop `solve` by setting `cancel`, it then finds nothing.
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> ExactPacker {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Keeps shapes off the given cells, counted from `INITIAL`. Anything
+    /// outside the rectangle is ignored.
+    pub fn with_obstacles(mut self, obstacles: &[GlobalPos]) -> ExactPacker {
+        for pos in obstacles {
+            let (x, y) = (pos.x - INITIAL.x, pos.y - INITIAL.y);
+            if (0..self.width).contains(&x) && (0..self.height).contains(&y) {
+                self.blocked.insert((y * self.width + x) as usize);
+            }
+        }
+
+        for rows in &mut self.rows_per_shape {
+            rows.retain(|row| !row.cells.intersects(&self.blocked));
+        }
+
+        self.slack = (self.cell_count - self.blocked.count()).saturating_sub(self.tile_count);
+        self
+    }
+
+    /// Returns a placement of every remaining shape inside the rectangle, if one exists.
+    pub fn solve(&mut self) -> Option<Vec<Placement>> {
+        if self.width <= 0 || self.height <= 0 || self.tile_count > self.cell_count - self.blocked.count() {
+            return None;
+        }
+
+        let mut occupied = self.blocked.clone();
+        let mut last_row = vec![None; self.rows_per_shape.len()];
+        let mut chosen = Vec::new();
+
+        if self.search(&mut occupied, &mut last_row, &mut chosen) {
+            return Some(chosen
+                .iter()
+                .map(|(shape_index, row_index)| self.rows_per_shape[*shape_index][*row_index].placement)
+                .collect());
+        }
+        None
+    }
+
+    fn search(
+        &mut self,
+        occupied: &mut CellSet,
+        last_row: &mut [Option<usize>],
+        chosen: &mut Vec<(usize, usize)>,
+    ) -> bool {
+        self.nodes += 1;
+
+        if self.cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        // Pick the shape with the fewest options left. Copies of a shape are
+        // interchangeable, so each copy only considers rows after the previous one.
+        let mut best: Option<(usize, Vec<usize>)> = None;
+        let mut coverable = occupied.clone();
+
+        for (shape_index, rows) in self.rows_per_shape.iter().enumerate() {
+            if self.remaining[shape_index] == 0 {
+                continue;
+            }
+
+            let start = last_row[shape_index].map_or(0, |row_index| row_index + 1);
+            let candidates: Vec<usize> = (start..rows.len())
+                .filter(|row_index| !rows[*row_index].cells.intersects(occupied))
+                .collect();
+
+            for row_index in &candidates {
+                coverable.union_with(&rows[*row_index].cells);
+            }
+
+            if best.as_ref().is_none_or(|(_, best_rows)| candidates.len() < best_rows.len()) {
+                best = Some((shape_index, candidates));
+            }
+        }
+
+        let Some((shape_index, candidates)) = best else {
+            // nothing left to place
+            return true;
+        };
+
+        // Cells no remaining shape can reach will stay empty.
+        if candidates.is_empty() || self.cell_count - coverable.count() > self.slack {
+            return false;
+        }
+
+        let previous_row = last_row[shape_index];
+        self.remaining[shape_index] -= 1;
+
+        for row_index in candidates {
+            occupied.toggle(&self.rows_per_shape[shape_index][row_index].cells);
+            last_row[shape_index] = Some(row_index);
+            chosen.push((shape_index, row_index));
+
+            if self.search(occupied, last_row, chosen) {
+                return true;
+            }
+
+            chosen.pop();
+            occupied.toggle(&self.rows_per_shape[shape_index][row_index].cells);
+        }
+
+        last_row[shape_index] = previous_row;
+        self.remaining[shape_index] += 1;
+        false
+    }
+}
+
+pub struct ExactSolution {
+    pub width: i32,
+    pub height: i32,
+    pub placements: Vec<Placement>,
+    /// Search nodes visited over every rectangle tried.
+    pub nodes: usize,
+}
+
+/// No rectangle up to `max_area` could hold the bag.
+pub struct ExactFailure {
+    pub max_area: i32,
+    /// Search nodes visited before giving up.
+    pub nodes: usize,
+}
+
+impl std::fmt::Display for ExactFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no packing found with area <= {}", self.max_area)
+    }
+}
+
+/// Tiles of every shape left in the bag.
+fn tile_count(bag: &ShapeBag) -> i32 {
+    (0..bag.shape_count())
+        .map(|shape_index| bag.shape(shape_index).tiles.len() as i32 * bag.remaining(shape_index) as i32)
+        .sum()
+}
+
+/// Packs the bag into a `width` × `height` rectangle anchored at `INITIAL`, if possible.
+pub fn fits(bag: &ShapeBag, width: i32, height: i32) -> Option<Vec<Placement>> {
+    ExactPacker::new(bag, width, height).solve()
+}
+
+/// Tries rectangles in increasing area, so the first fit is the smallest
+/// bounding rectangle. Gives up once `max_area` is exceeded or `cancel` is set.
+pub fn minimum_rectangle(
+    bag: &ShapeBag,
+    max_area: i32,
+    cancel: &Arc<AtomicBool>,
+) -> Result<ExactSolution, ExactFailure> {
+    let tile_count = tile_count(bag);
+
+    if tile_count == 0 {
+        return Ok(ExactSolution { width: 0, height: 0, placements: Vec::new(), nodes: 0 });
+    }
+
+    let mut nodes = 0;
+    for area in tile_count..=max_area {
+        // every permutation is allowed, so W×H and H×W are the same problem
+        let mut width = 1;
+        while width * width <= area {
+            if area % width == 0 {
+                let height = area / width;
+                let mut packer = ExactPacker::new(bag, width, height).with_cancel(cancel.clone());
+                let placements = packer.solve();
+                nodes += packer.nodes;
+
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(ExactFailure { max_area, nodes });
+                }
+
+                if let Some(placements) = placements {
+                    return Ok(ExactSolution { width, height, placements, nodes });
+                }
+            }
+            width += 1;
+        }
+    }
+
+    Err(ExactFailure { max_area, nodes })
+}
+
+/// Plans a provably smallest rectangle on an empty board and replays it.
+/// Anything it cannot plan for is finished greedily.
+pub struct ExactStrategy {
+    max_area: i32,
+    planned: Option<VecDeque<Placement>>,
+    /// Whether the plan covers the whole bag, rather than greedy having to finish it.
+    proven: bool,
+    /// Search nodes visited planning, whether or not a plan was found.
+    nodes: usize,
+}
+
+impl ExactStrategy {
+    pub fn new(max_area: i32) -> ExactStrategy {
+        ExactStrategy {
+            max_area,
+            planned: None,
+            proven: false,
+            nodes: 0,
+        }
+    }
+
+    /// Replays `placements` if there are any, greedy takes over otherwise.
+    fn plan(&mut self, board: &mut BoardState, placements: Option<Vec<Placement>>, nodes: usize) {
+        board.attempts += nodes;
+        self.nodes += nodes;
+        self.proven = placements.is_some();
+        self.planned = Some(placements.unwrap_or_default().into());
+    }
+}
+
+impl PlacementStrategy for ExactStrategy {
+    fn name(&self) -> &'static str {
+        "exact"
+    }
+
+    fn next_placement(&mut self, board: &mut BoardState, bag: &ShapeBag) -> Option<Placement> {
+        if self.planned.is_none() && board.is_empty() {
+            match minimum_rectangle(bag, self.max_area, &board.cancel) {
+                Ok(solution) => self.plan(board, Some(solution.placements), solution.nodes),
+                Err(failure) => self.plan(board, None, failure.nodes),
+            }
+        }
+
+        // a box or mask only has to be filled, as long as nothing has been placed yet
+        if let Some(container) = board.container.clone().filter(|_| self.planned.is_none()) {
+            if board.bounds.is_default() {
+                let mut packer = ExactPacker::new(bag, container.width(), container.height())
+                    .with_obstacles(&board.obstacles)
+                    .with_cancel(board.cancel.clone());
+                let placements = packer.solve();
+                self.plan(board, placements, packer.nodes);
+            }
+        }
+
+        match self.planned.as_mut().and_then(|planned| planned.pop_front()) {
+            Some(placement) => Some(placement),
+            None => GreedyStrategy.next_placement(board, bag),
+        }
+    }
+
+    fn report(&self) -> Option<String> {
+        Some(format!("proven={} nodes={}", self.proven, self.nodes))
+    }
+
+    fn reset(&mut self) {
+        self.planned = None;
+        self.proven = false;
+        self.nodes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const PENTOMINOES: &str = include_str!("../../assets/pieces/pentominoes.txt");
+
+    /// Every tile the placements cover, failing on overlaps.
+    fn covered(bag: &ShapeBag, placements: &[Placement]) -> HashSet<GlobalPos> {
+        let mut tiles = HashSet::new();
+        for placement in placements {
+            for tile in bag.iter_pos(&placement.permutation) {
+                assert!(tiles.insert(tile + placement.pos), "Placements overlap at {:?}", tile + placement.pos);
+            }
+        }
+        tiles
+    }
+
+    fn inside(tiles: &HashSet<GlobalPos>, width: i32, height: i32) -> bool {
+        tiles.iter().all(|pos| {
+            (INITIAL.x..INITIAL.x + width).contains(&pos.x) && (INITIAL.y..INITIAL.y + height).contains(&pos.y)
+        })
+    }
+
+    #[test]
+    fn pentominoes_tile_6_by_10() {
+        let bag = ShapeBag::parse(PENTOMINOES, 1).unwrap();
+        let placements = ExactPacker::new(&bag, 10, 6).solve().expect("The pentominoes tile 6x10.");
+
+        assert_eq!(placements.len(), 12);
+        let tiles = covered(&bag, &placements);
+        assert_eq!(tiles.len(), 60);
+        assert!(inside(&tiles, 10, 6));
+    }
+
+    #[test]
+    fn roomy_rectangle_fits_the_bag() {
+        let bag = ShapeBag::load(1);
+        let placements = fits(&bag, 12, 12).expect("Six shapes fit 12x12.");
+
+        assert_eq!(placements.len(), 6);
+        let tiles = covered(&bag, &placements);
+        assert_eq!(tiles.len(), 48);
+        assert!(inside(&tiles, 12, 12));
+    }
+
+    #[test]
+    fn too_small_rectangle_does_not_fit() {
+        // 42 cells for 48 tiles
+        assert!(fits(&ShapeBag::load(1), 6, 7).is_none());
+    }
+
+    #[test]
+    fn minimum_rectangle_of_an_empty_bag_is_empty() {
+        let solution = minimum_rectangle(&ShapeBag::load(0), 10, &Arc::default()).ok().unwrap();
+
+        assert_eq!((solution.width, solution.height), (0, 0));
+        assert!(solution.placements.is_empty());
+    }
+
+    #[test]
+    fn minimum_rectangle_finds_the_smallest_area() {
+        let bag = ShapeBag::parse("XX\nXX\n\nX\nX\nX\n", 1).unwrap();
+        let solution = minimum_rectangle(&bag, 100, &Arc::default()).ok().unwrap();
+
+        // the bar leaves a gap next to the square in 2x4, so 3x3 is the first that fits
+        assert_eq!(solution.width * solution.height, 9);
+        assert!(inside(&covered(&bag, &solution.placements), solution.width, solution.height));
+    }
+
+    #[test]
+    fn minimum_rectangle_gives_up_past_max_area() {
+        let failure = minimum_rectangle(&ShapeBag::load(1), 47, &Arc::default()).err().unwrap();
+
+        assert_eq!(failure.to_string(), "no packing found with area <= 47");
+    }
+
+    #[test]
+    fn obstacles_are_packed_around() {
+        let bag = ShapeBag::parse("count: 4\nXX\n", 1).unwrap();
+        let corner = GlobalPos { x: 0, y: 0 };
+
+        let placements = ExactPacker::new(&bag, 3, 3).with_obstacles(&[corner]).solve();
+        let tiles = covered(&bag, &placements.expect("Four dominoes fill 3x3 without a corner."));
+        assert!(!tiles.contains(&corner));
+
+        // every 2x2 spot in a 3x3 covers the center
+        let bag = ShapeBag::parse("XX\nXX\n", 1).unwrap();
+        let center = GlobalPos { x: 1, y: 1 };
+        assert!(ExactPacker::new(&bag, 3, 3).with_obstacles(&[center]).solve().is_none());
+    }
+
+    #[test]
+    fn cancelled_search_finds_nothing() {
+        let bag = ShapeBag::parse(PENTOMINOES, 1).unwrap();
+        let cancel = Arc::new(AtomicBool::new(true));
+
+        assert!(ExactPacker::new(&bag, 10, 6).with_cancel(cancel).solve().is_none());
+    }
+}
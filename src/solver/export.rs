@@ -0,0 +1,110 @@
+//! Renders a finished board for pasting into chat, reports and the like.
+
+use super::{Bounds, GlobalPos, Solver};
+
+/// `A` to `Z`, then `a` to `z`, then `?` for everything past that, since `#` marks obstacles.
+pub fn shape_letter(shape_index: usize) -> char {
+    const LETTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    LETTERS.get(shape_index).map(|letter| *letter as char).unwrap_or('?')
+}
+
+impl Solver {
+    /// Every placed tile with the index of the shape covering it.
+    pub fn iter_tiles(&self) -> impl Iterator<Item = (usize, GlobalPos)> + '_ {
+        self.placements.iter().flat_map(|placement| {
+            self.bag
+                .mask(&placement.permutation)
+                .tiles
+                .iter()
+                .map(move |tile| (placement.permutation.index, *tile + placement.pos))
+        })
+    }
+
+    /// What's worth drawing: the placed tiles, plus the whole box if there is one.
+    pub fn drawn_bounds(&self) -> Bounds {
+        let mut bounds = self.board.bounds.clone();
+        if let Some(container) = &self.board.container {
+            bounds.expand(&GlobalPos { x: container.min_x, y: container.min_y });
+            bounds.expand(&GlobalPos { x: container.max_x, y: container.max_y });
+        }
+        bounds
+    }
+
+    /// Obstacles inside `drawn_bounds`, leaving out the walls around a box.
+    pub fn drawn_obstacles(&self) -> impl Iterator<Item = &GlobalPos> + '_ {
+        let bounds = self.drawn_bounds();
+        self.board.obstacles.iter().filter(move |pos| bounds.contains(pos))
+    }
+
+    /// The board within `drawn_bounds` as one letter per shape index, `#` for
+    /// obstacles and `.` for empty cells, rows going down with y like
+    /// `Shape::from_string` reads them.
+    pub fn to_ascii(&self) -> String {
+        let bounds = &self.drawn_bounds();
+        if bounds.is_default() {
+            return String::new();
+        }
+
+        let mut grid = vec![vec!['.'; bounds.width() as usize]; bounds.height() as usize];
+        for pos in self.drawn_obstacles() {
+            let (x, y) = cell(bounds, pos);
+            grid[y][x] = '#';
+        }
+        for (shape_index, pos) in self.iter_tiles() {
+            let (x, y) = cell(bounds, &pos);
+            grid[y][x] = shape_letter(shape_index);
+        }
+
+        grid.into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect()
+    }
+}
+
+/// Grid cell of `pos` inside `bounds`, counted from the top left.
+fn cell(bounds: &Bounds, pos: &GlobalPos) -> (usize, usize) {
+    ((pos.x - bounds.min_x) as usize, (pos.y - bounds.min_y) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{BoardMask, Permutation, Placement, ShapeBag, ShapePermutation};
+
+    fn place(solver: &mut Solver, index: usize, x: i32, y: i32) {
+        solver.place(&Placement {
+            permutation: ShapePermutation { index, permutation: Permutation::from_index(0) },
+            pos: GlobalPos { x, y },
+        });
+    }
+
+    #[test]
+    fn ascii_shows_a_letter_per_shape_and_dots_for_empty_cells() {
+        let mut solver = Solver::new(ShapeBag::parse("XX\n\nX\nX\n", 1).unwrap(), 0);
+        place(&mut solver, 0, 0, 0);
+        place(&mut solver, 1, 2, 1);
+
+        assert_eq!(solver.to_ascii(), "AA.\n..B\n..B\n");
+    }
+
+    #[test]
+    fn ascii_marks_obstacles_and_shapes_past_the_letters() {
+        // a domino, then single tiles up to index 52, past the last letter
+        let pieces: Vec<&str> = std::iter::once("XX\n").chain(std::iter::repeat_n("X\n", 52)).collect();
+        let bag = ShapeBag::parse(&pieces.join("\n"), 1).unwrap();
+
+        let mut solver = Solver::new(bag, 0).with_mask(&BoardMask::parse("...\n#..\n").unwrap());
+        place(&mut solver, 0, 0, 0);
+        place(&mut solver, 52, 2, 1);
+
+        assert_eq!(solver.to_ascii(), "AA.\n#.?\n");
+        assert_eq!(shape_letter(26), 'a');
+        assert_eq!(shape_letter(51), 'z');
+    }
+
+    #[test]
+    fn empty_board_is_empty_text() {
+        let solver = Solver::new(ShapeBag::load(1), 0);
+        assert_eq!(solver.to_ascii(), "");
+    }
+}
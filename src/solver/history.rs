@@ -0,0 +1,124 @@
+//! Undo and redo. Scores, pockets and the border depend on every earlier
+//! placement, so stepping back rebuilds the board from the placements kept.
+
+use super::{place, Placement, Solver};
+
+impl Solver {
+    /// Placements made plus those that can be redone.
+    pub fn history_len(&self) -> usize {
+        self.placements.len() + self.undone.len()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    pub fn undo(&mut self) -> Option<Placement> {
+        let placement = *self.placements.last()?;
+        self.rewind_to(self.placements.len() - 1);
+        Some(placement)
+    }
+
+    pub fn redo(&mut self) -> Option<Placement> {
+        let placement = self.undone.pop()?;
+        place(&mut self.board, &mut self.bag, &placement);
+        self.placements.push(placement);
+        Some(placement)
+    }
+
+    /// Undoes or redoes until `step` placements are on the board, or as close as the history allows.
+    pub fn rewind_to(&mut self, step: usize) {
+        let step = step.min(self.history_len());
+
+        if step >= self.placements.len() {
+            while self.placements.len() < step && self.redo().is_some() {}
+            return;
+        }
+
+        for placement in &self.placements {
+            self.bag.put_back(placement.permutation.index);
+        }
+
+        // attempts count work spent, stepping back doesn't undo that
+        let attempts = self.board.attempts;
+        self.board.reset(self.board.max_attempts);
+        self.board.attempts = attempts;
+        self.strategy.reset();
+
+        let undone = self.placements.split_off(step);
+        self.undone.extend(undone.into_iter().rev());
+
+        for placement in &self.placements {
+            place(&mut self.board, &mut self.bag, placement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{GlobalPos, ShapeBag};
+
+    /// Taken cells, scored positions and pieces left, in a comparable order.
+    fn snapshot(solver: &Solver) -> (Vec<GlobalPos>, Vec<(GlobalPos, i32)>, Vec<u16>) {
+        let bounds = &solver.board.bounds;
+        let occupied = (bounds.min_y - 1..=bounds.max_y + 1)
+            .flat_map(|y| (bounds.min_x - 1..=bounds.max_x + 1).map(move |x| GlobalPos { x, y }))
+            .filter(|pos| solver.board.occupied.get(pos))
+            .collect();
+
+        let mut scored: Vec<(GlobalPos, i32)> = solver.board.scored_positions.iter().map(|(pos, score)| (*pos, *score)).collect();
+        scored.sort();
+
+        let remaining = (0..solver.bag.shape_count()).map(|index| solver.bag.remaining(index)).collect();
+        (occupied, scored, remaining)
+    }
+
+    fn solved() -> Solver {
+        let mut solver = Solver::new(ShapeBag::load(1), 0).with_seed(3);
+        solver.solve();
+        solver
+    }
+
+    #[test]
+    fn rewind_then_redo_restores_the_board() {
+        let mut solver = solved();
+        let finished = snapshot(&solver);
+        let total = solver.placements.len();
+
+        solver.rewind_to(2);
+        assert_eq!(solver.placements.len(), 2);
+        assert_eq!(solver.history_len(), total);
+
+        // the same board as only placing the first two
+        let mut fresh = Solver::new(ShapeBag::load(1), 0).with_seed(3);
+        for placement in &solver.placements {
+            fresh.place(placement);
+        }
+        assert_eq!(snapshot(&solver), snapshot(&fresh));
+
+        while solver.redo().is_some() {}
+        assert_eq!(solver.placements.len(), total);
+        assert_eq!(snapshot(&solver), finished);
+
+        solver.rewind_to(0);
+        solver.rewind_to(total);
+        assert_eq!(snapshot(&solver), finished);
+    }
+
+    #[test]
+    fn placing_after_undo_drops_the_redo_stack() {
+        let mut solver = solved();
+        let total = solver.placements.len();
+
+        let undone = solver.undo().unwrap();
+        solver.undo().unwrap();
+        assert!(solver.can_redo());
+        assert_eq!(solver.history_len(), total);
+
+        solver.place(&undone);
+        assert!(!solver.can_redo());
+        assert_eq!(solver.history_len(), total - 1);
+        assert!(solver.redo().is_none());
+    }
+}
@@ -0,0 +1,149 @@
+//! Flood fill for empty pockets the placed tiles have walled in.
+
+use std::collections::HashSet;
+
+use super::{iter_moore, Bitboard, BoardState, Bounds, GlobalPos};
+
+impl BoardState {
+    /// Empty region around `start`, where `is_filled` decides what counts as a wall.
+    /// Returns `None` if the region reaches outside `bounds`, i.e. it is not enclosed,
+    /// or once it grows past `limit` cells.
+    pub fn enclosed_region(
+        &self,
+        start: GlobalPos,
+        is_filled: impl Fn(&GlobalPos) -> bool,
+        bounds: &Bounds,
+        limit: usize,
+    ) -> Option<Vec<GlobalPos>> {
+
+        // counting holes can fill large regions, so look up visited cells in a bitboard
+        let mut visited = Bitboard::new();
+        let mut region = Vec::new();
+        let mut open = vec![start];
+
+        while let Some(pos) = open.pop() {
+            if is_filled(&pos) || visited.get(&pos) {
+                continue;
+            }
+
+            if !bounds.contains(&pos) || region.len() >= limit {
+                return None;
+            }
+
+            visited.set(&pos);
+            region.push(pos);
+            open.extend(iter_moore(pos));
+        }
+
+        Some(region)
+    }
+
+    /// Enclosed pockets touching the tiles at `placed`, assuming they were placed.
+    /// Pockets bigger than `limit` are skipped.
+    pub fn pockets_around(
+        &self,
+        placed: &[GlobalPos],
+        bounds: &Bounds,
+        limit: usize,
+    ) -> Vec<Vec<GlobalPos>> {
+
+        let is_filled = |pos: &GlobalPos| placed.contains(pos) || self.is_blocked(pos);
+
+        let mut pockets: Vec<Vec<GlobalPos>> = Vec::new();
+        let mut in_pocket = Bitboard::new();
+        for global_pos in placed {
+            for neighbor in iter_moore(*global_pos) {
+                if is_filled(&neighbor) || in_pocket.get(&neighbor) {
+                    continue;
+                }
+
+                if let Some(pocket) = self.enclosed_region(neighbor, is_filled, bounds, limit) {
+                    for pos in &pocket {
+                        in_pocket.set(pos);
+                    }
+                    pockets.push(pocket);
+                }
+            }
+        }
+
+        pockets
+    }
+
+    /// Marks border tiles in enclosed pockets smaller than `min_size` as dead,
+    /// since no remaining shape can ever go there.
+    pub fn prune_pockets(&mut self, min_size: usize) {
+        if min_size < 2 {
+            return;
+        }
+
+        let mut dead = HashSet::new();
+        for (global_pos, border) in self.border.iter() {
+            if border.dead || dead.contains(global_pos) {
+                continue;
+            }
+
+            let is_filled = |pos: &GlobalPos| self.is_blocked(pos);
+            if let Some(pocket) = self.enclosed_region(*global_pos, is_filled, self.pocket_bounds(&self.bounds), min_size - 1) {
+                dead.extend(pocket);
+            }
+        }
+
+        for global_pos in dead {
+            if let Some(border) = self.border.get_mut(&global_pos) {
+                border.dead = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::ShapeMask;
+
+    fn pos(x: i32, y: i32) -> GlobalPos {
+        GlobalPos { x, y }
+    }
+
+    /// Board with a 3 × 3 block placed, minus the cells in `open`.
+    fn block_without(open: &[GlobalPos]) -> BoardState {
+        let tiles: Vec<GlobalPos> = (0..3)
+            .flat_map(|y| (0..3).map(move |x| pos(x, y)))
+            .filter(|tile| !open.contains(tile))
+            .collect();
+
+        let mut board = BoardState::new(0);
+        board.place(&tiles);
+        board
+    }
+
+    #[test]
+    fn enclosed_single_cell_is_pruned_once_pieces_need_two() {
+        let mut board = block_without(&[pos(1, 1)]);
+
+        board.prune_pockets(1);
+        assert!(!board.border[&pos(1, 1)].dead);
+
+        board.prune_pockets(2);
+        assert!(board.border[&pos(1, 1)].dead);
+        assert!(board.border.iter().filter(|(global_pos, _)| **global_pos != pos(1, 1)).all(|(_, border)| !border.dead));
+    }
+
+    #[test]
+    fn only_enclosed_regions_count_as_holes() {
+        let mut board = block_without(&[pos(1, 0), pos(1, 1)]);
+        board.weights.holes = 1;
+        let tile = ShapeMask::new(vec![pos(0, 0)]);
+
+        // (1, 1) is still open to the outside through (1, 0)
+        let open = board.get_placement_terms(&pos(3, 0), &tile, 0, 4).unwrap();
+        assert_eq!(open.holes, 0);
+
+        // capping it walls (1, 1) in
+        let capped = board.get_placement_terms(&pos(1, 0), &tile, 0, 4).unwrap();
+        assert_eq!(capped.holes, -1);
+
+        // unless pieces need two cells, then the placement is refused
+        assert!(board.get_placement_terms(&pos(1, 0), &tile, 2, 4).is_none());
+    }
+}
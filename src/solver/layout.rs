@@ -0,0 +1,253 @@
+//! Finished boards as files, so packings can be shared and shown again.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    BoardMask, BoxSize, GlobalPos, Permutation, Placement, ShapeBag, ShapePermutation, Solver, StrategyKind,
+    StrategySettings, INITIAL,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LayoutPlacement {
+    pub shape: usize,
+    pub permutation: Permutation,
+    pub pos: GlobalPos,
+}
+
+impl From<&Placement> for LayoutPlacement {
+    fn from(placement: &Placement) -> Self {
+        LayoutPlacement {
+            shape: placement.permutation.index,
+            permutation: placement.permutation.permutation,
+            pos: placement.pos,
+        }
+    }
+}
+
+impl From<&LayoutPlacement> for Placement {
+    fn from(placement: &LayoutPlacement) -> Self {
+        Placement {
+            permutation: ShapePermutation {
+                index: placement.shape,
+                permutation: placement.permutation,
+            },
+            pos: placement.pos,
+        }
+    }
+}
+
+/// The placements of a board plus what it took to make them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Layout {
+    pub n: u16,
+    pub crunch: usize,
+    pub seed: u64,
+    pub area: i32,
+    #[serde(default)]
+    pub strategy: String,
+    /// Beam width and exact area limit, so the strategy searches like it did.
+    #[serde(default)]
+    pub settings: StrategySettings,
+    /// Piece file the shape indices refer to, `None` for the built-in pieces.
+    #[serde(default)]
+    pub pieces: Option<String>,
+    #[serde(default)]
+    pub counts: Vec<Option<u16>>,
+    /// Box the board was packed into, if any.
+    #[serde(default)]
+    pub container: Option<BoxSize>,
+    /// Cells blocked inside the box when it came from a mask, counted from its top left.
+    #[serde(default)]
+    pub blocked: Vec<GlobalPos>,
+    pub placements: Vec<LayoutPlacement>,
+}
+
+impl Layout {
+    pub fn new(
+        solver: &Solver,
+        n: u16,
+        pieces: Option<String>,
+        counts: Vec<Option<u16>>,
+        settings: StrategySettings,
+    ) -> Layout {
+        Layout {
+            n,
+            crunch: solver.board.max_attempts,
+            seed: solver.board.seed,
+            area: solver.board.bounds.area(),
+            strategy: solver.strategy.name().into(),
+            settings,
+            pieces,
+            counts,
+            container: solver.board.container.as_ref().map(|container| BoxSize {
+                width: container.width(),
+                height: container.height(),
+            }),
+            blocked: solver.drawn_obstacles().map(|pos| *pos - INITIAL).collect(),
+            placements: solver.placements.iter().map(LayoutPlacement::from).collect(),
+        }
+    }
+
+    /// RON for `.ron` files, JSON otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let text = if is_ron(path) {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|err| format!("Could not write layout: {}", err))?
+        } else {
+            serde_json::to_string_pretty(self)
+                .map_err(|err| format!("Could not write layout: {}", err))?
+        };
+
+        std::fs::write(path, text)
+            .map_err(|err| format!("Could not write {}: {}", path.display(), err))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Layout, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+
+        if is_ron(path) {
+            ron::from_str(&text).map_err(|err| format!("Invalid layout {}: {}", path.display(), err))
+        } else {
+            serde_json::from_str(&text).map_err(|err| format!("Invalid layout {}: {}", path.display(), err))
+        }
+    }
+
+    /// The bag the layout was made from, read from `pieces` if set.
+    pub fn bag(&self) -> Result<ShapeBag, String> {
+        let mut bag = match &self.pieces {
+            Some(path) => ShapeBag::from_file(path, self.n)?,
+            None => ShapeBag::load(self.n),
+        };
+        bag.set_counts(&self.counts)?;
+        Ok(bag)
+    }
+
+    /// The mask the board was packed into, if it had any blocked cells.
+    pub fn mask(&self) -> Option<BoardMask> {
+        let size = self.container.filter(|_| !self.blocked.is_empty())?;
+        Some(BoardMask {
+            width: size.width,
+            height: size.height,
+            blocked: self.blocked.clone(),
+        })
+    }
+
+    /// Empty board of the same shape as the one the layout was made on.
+    pub fn empty_solver(&self, bag: ShapeBag) -> Solver {
+        let solver = Solver::new(bag, self.crunch);
+        match (self.mask(), self.container) {
+            (Some(mask), _) => solver.with_mask(&mask),
+            (None, Some(size)) => solver.with_container(size),
+            (None, None) => solver,
+        }
+    }
+
+    /// Replays the placements on a fresh solver, refusing any that don't fit.
+    pub fn to_solver(&self, bag: ShapeBag) -> Result<Solver, String> {
+        let mut solver = self.empty_solver(bag).with_seed(self.seed);
+        if let Ok(kind) = self.strategy.parse::<StrategyKind>() {
+            solver.strategy = kind.build(&self.settings);
+        }
+
+        for (index, placement) in self.placements.iter().enumerate() {
+            solver.try_place(&placement.into())
+                .map_err(|message| format!("Placement {}: {}", index, message))?;
+        }
+
+        Ok(solver)
+    }
+}
+
+fn is_ron(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ron"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solved() -> (Solver, Layout) {
+        let settings = StrategySettings {
+            beam_width: 3,
+            max_area: 77,
+        };
+        let mut solver = Solver::new(ShapeBag::load(1), 0)
+            .with_strategy(StrategyKind::Beam.build(&settings))
+            .with_seed(5);
+        solver.solve();
+        let layout = Layout::new(&solver, 1, None, vec![Some(1)], settings);
+        (solver, layout)
+    }
+
+    fn same_placements(a: &[LayoutPlacement], b: &[LayoutPlacement]) -> bool {
+        a.len() == b.len()
+            && a.iter().zip(b).all(|(a, b)| {
+                a.shape == b.shape && a.permutation.index() == b.permutation.index() && a.pos == b.pos
+            })
+    }
+
+    fn round_trip(extension: &str) {
+        let (solver, layout) = solved();
+        let path = std::env::temp_dir().join(format!("anders-kluring-layout-{}.{}", std::process::id(), extension));
+
+        layout.save(&path).unwrap();
+        let loaded = Layout::load(&path);
+        let _ = std::fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.seed, 5);
+        assert_eq!(loaded.area, layout.area);
+        assert_eq!(loaded.counts, vec![Some(1)]);
+        assert_eq!(loaded.strategy, "beam");
+        assert_eq!(loaded.settings.beam_width, 3);
+        assert_eq!(loaded.settings.max_area, 77);
+        assert!(same_placements(&loaded.placements, &layout.placements));
+
+        let replayed = loaded.to_solver(loaded.bag().unwrap()).unwrap();
+        assert_eq!(replayed.board.bounds.area(), solver.board.bounds.area());
+        assert_eq!(replayed.bag.remaining_total(), solver.bag.remaining_total());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("json");
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        round_trip("ron");
+    }
+
+    #[test]
+    fn older_files_without_optional_fields_load() {
+        let layout: Layout = serde_json::from_str(
+            r#"{"n":1,"crunch":0,"seed":3,"area":0,"placements":[]}"#,
+        ).unwrap();
+
+        assert!(layout.pieces.is_none());
+        assert!(layout.counts.is_empty());
+        assert!(layout.container.is_none());
+        assert!(layout.blocked.is_empty());
+        assert!(layout.strategy.is_empty());
+        assert_eq!(layout.settings.beam_width, 1);
+    }
+
+    #[test]
+    fn overlapping_placements_are_refused() {
+        let (_, mut layout) = solved();
+        let first = layout.placements[0].clone();
+        layout.placements.push(first);
+
+        // enough pieces, so only the overlap is wrong
+        layout.n = 2;
+        layout.counts.clear();
+
+        let message = layout.to_solver(layout.bag().unwrap()).err().unwrap();
+        assert!(message.contains("overlaps"), "{}", message);
+    }
+}
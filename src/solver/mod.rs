@@ -0,0 +1,430 @@
+//! Bevy-free packing solver: feed it a `ShapeBag`, get back a list of placements.
+
+use std::{
+    collections::HashSet,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+pub use self::{
+    bitboard::{Bitboard, ShapeMask},
+    board::{BoardMask, BoardState, Border, Bounds, BoxSize, BLOCKED, INITIAL},
+    export::shape_letter,
+    layout::{Layout, LayoutPlacement},
+    pos::{GlobalPos, iter_moore},
+    render::{shape_color, RenderOptions},
+    restart::{RandomRestarts, RestartLimit},
+    score::{ScoreTerms, ScoreWeights},
+    shape::{Permutation, Shape, ShapeBag, ShapePermutation, PERMUTATIONS},
+    strategy::{GreedyStrategy, PlacementStrategy, StrategyKind, StrategySettings},
+};
+
+pub mod beam;
+pub mod exact;
+
+mod bitboard;
+mod board;
+mod export;
+mod history;
+mod holes;
+mod layout;
+mod parallel;
+mod pos;
+mod render;
+mod restart;
+mod score;
+mod shape;
+mod strategy;
+
+/// A shape permutation anchored at a position on the board.
+#[derive(Copy, Clone)]
+pub struct Placement {
+    pub permutation: ShapePermutation,
+    pub pos: GlobalPos,
+}
+
+/// A placement together with the score it got on the board it was found on.
+#[derive(Copy, Clone)]
+pub struct Candidate {
+    pub score: i32,
+    pub placement: Placement,
+}
+
+pub struct Solver {
+    pub board: BoardState,
+    pub bag: ShapeBag,
+    pub placements: Vec<Placement>,
+    pub strategy: Box<dyn PlacementStrategy>,
+    /// Undone placements, the next one to redo last.
+    undone: Vec<Placement>,
+}
+
+impl Solver {
+    pub fn new(bag: ShapeBag, max_attempts: usize) -> Solver {
+        Solver {
+            board: BoardState::new(max_attempts),
+            bag,
+            placements: Vec::new(),
+            strategy: Box::new(GreedyStrategy),
+            undone: Vec::new(),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: Box<dyn PlacementStrategy>) -> Solver {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Same seed, same bag and settings, same placements.
+    pub fn with_seed(mut self, seed: u64) -> Solver {
+        self.board.set_seed(seed);
+        self
+    }
+
+    /// Lets another thread stop the solve by setting `cancel`, even in the
+    /// middle of planning a whole board.
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Solver {
+        self.board.cancel = cancel;
+        self
+    }
+
+    pub fn reset(&mut self, count: u16, max_attempts: usize) {
+        self.bag.reset(count);
+        self.board.reset(max_attempts);
+        self.placements.clear();
+        self.undone.clear();
+        self.strategy.reset();
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.bag.is_empty()
+    }
+
+    /// Places the shape picked by the strategy, or returns `None` once nothing
+    /// more can be placed or the solve was cancelled.
+    pub fn step(&mut self) -> Option<Placement> {
+        if self.board.is_cancelled() {
+            return None;
+        }
+
+        let placement = self.strategy.next_placement(&mut self.board, &self.bag)?;
+
+        // a plan cut short by cancelling isn't worth placing from
+        if self.board.is_cancelled() {
+            return None;
+        }
+
+        self.place(&placement);
+        Some(placement)
+    }
+
+    /// Steps until the bag is empty and returns every placement made.
+    pub fn solve(&mut self) -> Vec<Placement> {
+        let mut placements = Vec::new();
+        while !self.is_done() {
+            match self.step() {
+                Some(placement) => placements.push(placement),
+                None => break,
+            }
+        }
+        placements
+    }
+
+    /// Places the shape, dropping anything that could have been redone.
+    pub fn place(&mut self, placement: &Placement) {
+        self.undone.clear();
+        place(&mut self.board, &mut self.bag, placement);
+        self.placements.push(*placement);
+    }
+
+    /// Like `place`, but refuses shapes that aren't in the bag or would overlap.
+    pub fn try_place(&mut self, placement: &Placement) -> Result<(), String> {
+        let index = placement.permutation.index;
+        if index >= self.bag.shape_count() {
+            return Err(format!("There is no shape {}", index));
+        }
+        if self.bag.remaining(index) == 0 {
+            return Err(format!("No shape {} left to place", index));
+        }
+        let mask = self.bag.mask(&placement.permutation);
+        if !self.board.is_inside(&(mask.min + placement.pos)) || !self.board.is_inside(&(mask.max + placement.pos)) {
+            return Err(format!("Shape {} sticks out of the box at {}, {}", index, placement.pos.x, placement.pos.y));
+        }
+        if self.board.occupied.intersects(mask, placement.pos) {
+            return Err(format!("Shape {} overlaps at {}, {}", index, placement.pos.x, placement.pos.y));
+        }
+
+        self.place(placement);
+        Ok(())
+    }
+
+    /// The `count` best placements the search finds on the current board, best
+    /// first. Nothing is placed and the attempts aren't counted.
+    pub fn hints(&self, count: usize) -> Vec<Candidate> {
+        let mut candidates = if self.board.is_empty() {
+            beam::first_candidates(&self.bag)
+        } else {
+            find_candidates(&mut self.board.clone(), &self.bag)
+        };
+
+        // sorted like `GreedyStrategy`, which takes the last of equal scores,
+        // so the first hint is the placement greedy would make
+        candidates.sort_by_key(|candidate| candidate.score);
+        candidates.reverse();
+
+        // neighboring border positions find the same placement
+        let mut seen = HashSet::new();
+        candidates.retain(|candidate| {
+            let permutation = &candidate.placement.permutation;
+            seen.insert((permutation.index, permutation.permutation.index(), candidate.placement.pos))
+        });
+
+        candidates.truncate(count);
+        candidates
+    }
+
+    /// Packs into a box from `INITIAL` instead of growing freely.
+    pub fn with_container(mut self, size: BoxSize) -> Solver {
+        self.board.set_container(size.width, size.height);
+        self
+    }
+
+    /// Packs into the open cells of `mask` only.
+    pub fn with_mask(mut self, mask: &BoardMask) -> Solver {
+        self.board.set_mask(mask);
+        self
+    }
+
+    /// Places `locked` before anything is solved, so the strategy has to build around them.
+    pub fn with_locked(mut self, locked: &[Placement]) -> Result<Solver, String> {
+        for (index, placement) in locked.iter().enumerate() {
+            self.try_place(placement)
+                .map_err(|message| format!("Locked placement {}: {}", index, message))?;
+        }
+        Ok(self)
+    }
+}
+
+/// Takes the shape out of the bag and blocks its tiles on the board.
+pub fn place(board: &mut BoardState, bag: &mut ShapeBag, placement: &Placement) {
+    if !bag.try_pop(placement.permutation.index) {
+        panic!("Tried to place shape that was unavailable.");
+    }
+
+    let positions: Vec<GlobalPos> = bag.mask(&placement.permutation)
+        .tiles
+        .iter()
+        .map(|shape_pos| *shape_pos + placement.pos)
+        .collect();
+
+    board.place(&positions);
+    board.prune_pockets(bag.min_available_size().unwrap_or(0));
+}
+
+/// Border positions scored per thread and batch when the crunch limit may cut the search short.
+const POSITIONS_PER_THREAD: usize = 4;
+
+/// Every attempt anchored on one border position, in enumeration order.
+struct PositionAttempts {
+    count: usize,
+    /// Candidates with the index of the attempt that found them.
+    candidates: Vec<(usize, Candidate)>,
+}
+
+fn score_position(state: &BoardState, bag: &ShapeBag, border_pos: &GlobalPos) -> PositionAttempts {
+    let mut attempts = PositionAttempts {
+        count: 0,
+        candidates: Vec::new(),
+    };
+
+    for shape in bag.iter_available() {
+
+        // pockets smaller than anything left to place are wasted
+        let min_pocket = bag.min_size_without(shape.index).unwrap_or(0);
+        let max_pocket = bag.max_size_without(shape.index).unwrap_or(0);
+
+        for orientation in &shape.orientations {
+            let permutation = ShapePermutation {
+                index: shape.index,
+                permutation: *orientation,
+            };
+
+            // Iterate every position in the shape as anchor
+            let mask = bag.mask(&permutation);
+
+            for shape_tile_pos in &mask.tiles {
+
+                let attempt_pos = *border_pos - *shape_tile_pos;
+
+                if let Some(score) = state.get_placement_score(&attempt_pos, mask, min_pocket, max_pocket) {
+                    attempts.candidates.push((attempts.count, Candidate {
+                        score,
+                        placement: Placement { permutation, pos: attempt_pos },
+                    }));
+                }
+
+                attempts.count += 1;
+            }
+        }
+    }
+
+    attempts
+}
+
+/// Scores every available shape permutation anchored on the border,
+/// stopping early once the crunch limit is hit. Border positions where
+/// nothing fits are marked dead.
+///
+/// Positions are scored in parallel, but the results are merged in border
+/// order, so the candidates and their order don't depend on the thread count.
+pub fn find_candidates(state: &mut BoardState, bag: &ShapeBag) -> Vec<Candidate> {
+
+    let best_positions = state.collect_candidate_positions(state.max_attempts > 0);
+    let mut attempts_count = 0;
+    let mut best_attempts = Vec::new();
+
+    // Iterate every edge position
+    let mut dead_candidates = HashSet::new();
+
+    let batch_size = if state.max_attempts > 0 {
+        parallel::thread_count() * POSITIONS_PER_THREAD
+    } else {
+        best_positions.len().max(1)
+    };
+
+    'outer: for batch in best_positions.chunks(batch_size) {
+
+        let scored = parallel::map_ordered(batch, |border_pos| score_position(state, bag, border_pos));
+
+        for (border_pos, attempts) in batch.iter().zip(scored) {
+
+            let any_match = !attempts.candidates.is_empty();
+            let mut candidates = attempts.candidates.into_iter().peekable();
+            for attempt_index in 0..attempts.count {
+
+                // early out if we hit maximum crunch
+                if state.max_attempts > 0
+                    && attempts_count > state.max_attempts
+                    && best_attempts.len() > 1 {
+                    break 'outer;
+                }
+
+                attempts_count += 1;
+
+                if let Some((_, candidate)) = candidates.next_if(|(index, _)| *index == attempt_index) {
+                    best_attempts.push(candidate);
+                }
+            }
+
+            if !any_match {
+                dead_candidates.insert(*border_pos);
+            }
+        }
+    }
+
+    for dead_pos in dead_candidates {
+        if let Some(border) = state.border.get_mut(&dead_pos) {
+            border.dead = true;
+        }
+    }
+
+    state.attempts += attempts_count;
+
+    best_attempts
+}
+
+/// Runs a full greedy solve of `bag` and returns the placements in order.
+pub fn solve(bag: ShapeBag, max_attempts: usize) -> Vec<Placement> {
+    Solver::new(bag, max_attempts).solve()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domino(pos: GlobalPos) -> Placement {
+        Placement {
+            permutation: ShapePermutation { index: 0, permutation: Permutation::from_index(0) },
+            pos,
+        }
+    }
+
+    fn dominoes(count: u16) -> ShapeBag {
+        ShapeBag::parse("XX\n", count).unwrap()
+    }
+
+    fn solve_seeded(max_attempts: usize, seed: u64) -> Vec<(usize, usize, GlobalPos)> {
+        let mut solver = Solver::new(ShapeBag::load(2), max_attempts).with_seed(seed);
+        solver.solve()
+            .into_iter()
+            .map(|placement| (placement.permutation.index, placement.permutation.permutation.index(), placement.pos))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_placements() {
+        // with a crunch limit, border positions are scored in parallel batches
+        for max_attempts in [0, 500] {
+            let placements = solve_seeded(max_attempts, 11);
+            assert_eq!(placements.len(), 12);
+            assert_eq!(solve_seeded(max_attempts, 11), placements);
+        }
+
+        assert_ne!(solve_seeded(0, 11), solve_seeded(0, 12));
+    }
+
+    #[test]
+    fn with_locked_places_before_solving() {
+        let locked = [domino(GlobalPos { x: 0, y: 0 }), domino(GlobalPos { x: 0, y: 1 })];
+        let mut solver = Solver::new(dominoes(4), 0).with_locked(&locked).unwrap();
+
+        assert_eq!(solver.bag.remaining_total(), 2);
+        solver.solve();
+        assert_eq!(solver.placements.len(), 4);
+        assert_eq!(solver.placements[1].pos, GlobalPos { x: 0, y: 1 });
+    }
+
+    #[test]
+    fn with_locked_rejects_overlaps() {
+        let locked = [domino(GlobalPos { x: 0, y: 0 }), domino(GlobalPos { x: 1, y: 0 })];
+        let message = Solver::new(dominoes(2), 0).with_locked(&locked).err().unwrap();
+
+        assert!(message.starts_with("Locked placement 1"), "{}", message);
+        assert!(message.contains("overlaps"), "{}", message);
+    }
+
+    #[test]
+    fn with_locked_rejects_more_pieces_than_the_bag_has() {
+        let locked = [domino(GlobalPos { x: 0, y: 0 }), domino(GlobalPos { x: 0, y: 1 })];
+        assert!(Solver::new(dominoes(1), 0).with_locked(&locked).is_err());
+    }
+
+    #[test]
+    fn with_locked_rejects_placements_outside_the_box() {
+        let size = BoxSize { width: 2, height: 2 };
+        let solver = Solver::new(dominoes(1), 0).with_container(size);
+
+        assert!(solver.with_locked(&[domino(GlobalPos { x: 1, y: 0 })]).is_err());
+    }
+
+    #[test]
+    fn with_container_solves_a_box_that_fits() {
+        let mut solver = Solver::new(dominoes(4), 0).with_container(BoxSize { width: 4, height: 2 });
+        solver.solve();
+
+        assert!(solver.is_done());
+        assert_eq!(solver.board.bounds.area(), 8);
+
+        let mut solver = Solver::new(ShapeBag::load(1), 0)
+            .with_container(BoxSize { width: 10, height: 10 })
+            .with_seed(1);
+        solver.solve();
+
+        assert!(solver.is_done());
+        let container = solver.board.container.as_ref().unwrap();
+        for placement in &solver.placements {
+            for shape_pos in solver.bag.iter_pos(&placement.permutation) {
+                assert!(container.contains(&(shape_pos + placement.pos)));
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Order preserving parallel map: rayon on native targets, a plain loop on wasm.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn map_ordered<T, R>(items: &[T], f: impl Fn(&T) -> R + Sync + Send) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    use rayon::prelude::*;
+
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn thread_count() -> usize {
+    rayon::current_num_threads()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn thread_count() -> usize {
+    1
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn map_ordered<T, R>(items: &[T], f: impl Fn(&T) -> R) -> Vec<R> {
+    items.iter().map(f).collect()
+}
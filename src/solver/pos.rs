@@ -0,0 +1,39 @@
+use std::ops::{Sub, Add};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GlobalPos {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Add for GlobalPos {
+    type Output = GlobalPos;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        GlobalPos { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
+}
+
+impl Sub for GlobalPos {
+    type Output = GlobalPos;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        GlobalPos { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
+
+pub fn iter_moore(tile_pos: GlobalPos) -> impl Iterator<Item = GlobalPos> {
+    const NEIGHBORHOOD: [(i32, i32); 4] = [
+        ( 1, 0),
+        ( 0, 1),
+        (-1, 0),
+        ( 0,-1),
+    ];
+
+    NEIGHBORHOOD.iter().map(move |xy| GlobalPos { 
+        x: (tile_pos.x + xy.0),
+        y: (tile_pos.y + xy.1),
+     })
+}
@@ -0,0 +1,362 @@
+//! Headless PNG and SVG rendering of a board, for reports without screenshots.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    path::Path,
+};
+
+use image::{Rgba, RgbaImage};
+
+use super::{GlobalPos, Solver};
+
+const EMPTY_COLOR: [u8; 3] = [255, 255, 255];
+const GRID_COLOR: [u8; 3] = [210, 210, 210];
+const OUTLINE_COLOR: [u8; 3] = [40, 40, 40];
+const OBSTACLE_COLOR: [u8; 3] = [90, 90, 90];
+
+#[derive(Clone)]
+pub struct RenderOptions {
+    /// Side of one tile in pixels.
+    pub cell_size: u32,
+    pub grid_lines: bool,
+    pub outlines: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            cell_size: 16,
+            grid_lines: false,
+            outlines: true,
+        }
+    }
+}
+
+/// Evenly spread hues, so neighboring shape indices look different.
+pub fn shape_color(shape_index: usize) -> [u8; 3] {
+    let hue = (shape_index as f32 * 137.508) % 360.;
+    let (saturation, lightness): (f32, f32) = (0.6, 0.6);
+
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let x = chroma * (1. - ((hue / 60.) % 2. - 1.).abs());
+    let m = lightness - chroma / 2.;
+
+    let (r, g, b) = match (hue / 60.) as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+
+    [r, g, b].map(|channel| ((channel + m) * 255.).round() as u8)
+}
+
+impl Solver {
+    /// Placement and shape index covering each tile.
+    fn tile_owners(&self) -> HashMap<GlobalPos, (usize, usize)> {
+        let mut owners = HashMap::new();
+        for (placement_index, placement) in self.placements.iter().enumerate() {
+            for tile in &self.bag.mask(&placement.permutation).tiles {
+                owners.insert(*tile + placement.pos, (placement_index, placement.permutation.index));
+            }
+        }
+        owners
+    }
+
+    /// The board within `drawn_bounds`, rows going down with y like `to_ascii`.
+    pub fn to_image(&self, options: &RenderOptions) -> RgbaImage {
+        let bounds = &self.drawn_bounds();
+        if bounds.is_default() {
+            return RgbaImage::new(1, 1);
+        }
+
+        let cell = options.cell_size.max(1);
+        let width = bounds.width() as u32;
+        let height = bounds.height() as u32;
+        let owners = self.tile_owners();
+        let obstacles: HashSet<&GlobalPos> = self.drawn_obstacles().collect();
+
+        let owner = |x: i64, y: i64| {
+            owners.get(&GlobalPos {
+                x: bounds.min_x + x as i32,
+                y: bounds.min_y + y as i32,
+            })
+        };
+
+        let mut image = RgbaImage::from_pixel(width * cell, height * cell, rgba(EMPTY_COLOR));
+
+        for (pixel_x, pixel_y, pixel) in image.enumerate_pixels_mut() {
+            let (x, y) = ((pixel_x / cell) as i64, (pixel_y / cell) as i64);
+            let (inner_x, inner_y) = (pixel_x % cell, pixel_y % cell);
+            let this = owner(x, y);
+
+            if let Some((_, shape_index)) = this {
+                *pixel = rgba(shape_color(*shape_index));
+            } else if obstacles.contains(&GlobalPos { x: bounds.min_x + x as i32, y: bounds.min_y + y as i32 }) {
+                *pixel = rgba(OBSTACLE_COLOR);
+            }
+
+            let on_left = inner_x == 0;
+            let on_top = inner_y == 0;
+            let on_right = inner_x == cell - 1;
+            let on_bottom = inner_y == cell - 1;
+
+            if options.grid_lines && (on_left || on_top) {
+                *pixel = rgba(GRID_COLOR);
+            }
+
+            if options.outlines && this.is_some() {
+                // edge of the piece where the neighbor belongs to another placement
+                let differs = |dx: i64, dy: i64| {
+                    owner(x + dx, y + dy).map(|(placement, _)| placement) != this.map(|(placement, _)| placement)
+                };
+
+                if (on_left && differs(-1, 0))
+                    || (on_right && differs(1, 0))
+                    || (on_top && differs(0, -1))
+                    || (on_bottom && differs(0, 1)) {
+                    *pixel = rgba(OUTLINE_COLOR);
+                }
+            }
+        }
+
+        image
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>, options: &RenderOptions) -> Result<(), String> {
+        let path = path.as_ref();
+        self.to_image(options)
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|err| format!("Could not write {}: {}", path.display(), err))
+    }
+
+    /// One path per placed piece, in the same layout as `to_image`.
+    pub fn to_svg(&self, options: &RenderOptions) -> String {
+        let bounds = &self.drawn_bounds();
+        let cell = options.cell_size.max(1) as i32;
+        let (width, height) = if bounds.is_default() {
+            (0, 0)
+        } else {
+            (bounds.width(), bounds.height())
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+            width * cell, height * cell, width * cell, height * cell,
+        );
+        let _ = writeln!(svg, r#"  <rect width="100%" height="100%" fill="{}"/>"#, hex(EMPTY_COLOR));
+
+        for pos in self.drawn_obstacles() {
+            let _ = writeln!(
+                svg,
+                r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                (pos.x - bounds.min_x) * cell, (pos.y - bounds.min_y) * cell, cell, cell,
+                hex(OBSTACLE_COLOR),
+            );
+        }
+
+        if options.grid_lines {
+            let _ = write!(svg, r#"  <path stroke="{}" stroke-width="1" d=""#, hex(GRID_COLOR));
+            for x in 0..=width {
+                let _ = write!(svg, "M{} 0V{}", x * cell, height * cell);
+            }
+            for y in 0..=height {
+                let _ = write!(svg, "M0 {}H{}", y * cell, width * cell);
+            }
+            let _ = writeln!(svg, r#""/>"#);
+        }
+
+        let stroke = if options.outlines {
+            format!(r#" stroke="{}" stroke-width="1""#, hex(OUTLINE_COLOR))
+        } else {
+            String::new()
+        };
+
+        for placement in &self.placements {
+            let tiles: Vec<GlobalPos> = self.bag
+                .mask(&placement.permutation)
+                .tiles
+                .iter()
+                .map(|tile| *tile + placement.pos)
+                .collect();
+
+            // one subpath per loop, even-odd filling leaves holes empty
+            let mut path = String::new();
+            for corners in outlines(&tiles) {
+                for (index, corner) in corners.iter().enumerate() {
+                    let command = if index == 0 { 'M' } else { 'L' };
+                    let _ = write!(path, "{}{},{}", command, (corner.x - bounds.min_x) * cell, (corner.y - bounds.min_y) * cell);
+                }
+                path.push('Z');
+            }
+
+            let _ = writeln!(
+                svg,
+                r#"  <path d="{}" fill="{}" fill-rule="evenodd"{}/>"#,
+                path,
+                hex(shape_color(placement.permutation.index)),
+                stroke,
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn save_svg(&self, path: impl AsRef<Path>, options: &RenderOptions) -> Result<(), String> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_svg(options))
+            .map_err(|err| format!("Could not write {}: {}", path.display(), err))
+    }
+}
+
+/// Corner loops around a set of tiles, one per boundary: the outside goes
+/// clockwise with y going down, holes go the other way. Points in the middle of
+/// straight edges are left out. A tile's corner `(x, y)` is its top left.
+fn outlines(tiles: &[GlobalPos]) -> Vec<Vec<GlobalPos>> {
+    let corner = |x, y| GlobalPos { x, y };
+
+    // every tile side without a tile behind it, by where it starts; tiles
+    // touching only at a corner start two sides there
+    let mut edges: HashMap<GlobalPos, Vec<GlobalPos>> = HashMap::new();
+    for tile in tiles {
+        let (x, y) = (tile.x, tile.y);
+        let has = |dx, dy| tiles.contains(&GlobalPos { x: x + dx, y: y + dy });
+        let mut edge = |from, to| edges.entry(from).or_default().push(to);
+
+        if !has(0, -1) { edge(corner(x, y), corner(x + 1, y)); }
+        if !has(1, 0) { edge(corner(x + 1, y), corner(x + 1, y + 1)); }
+        if !has(0, 1) { edge(corner(x + 1, y + 1), corner(x, y + 1)); }
+        if !has(-1, 0) { edge(corner(x, y + 1), corner(x, y)); }
+    }
+
+    let mut loops = Vec::new();
+    while let Some(start) = edges.iter().filter(|(_, ends)| !ends.is_empty()).map(|(start, _)| *start).min() {
+        let mut points = vec![start];
+        let mut current = start;
+        let mut direction: Option<GlobalPos> = None;
+
+        while let Some(ends) = edges.get_mut(&current).filter(|ends| !ends.is_empty()) {
+            // at a pinch take the right turn, so each loop stays around its own tiles
+            let turn = |end: &GlobalPos| match direction {
+                None => 0,
+                Some(direction) => {
+                    let step = *end - current;
+                    if step == (GlobalPos { x: -direction.y, y: direction.x }) {
+                        0
+                    } else if step == direction {
+                        1
+                    } else {
+                        2
+                    }
+                },
+            };
+            let (index, _) = ends.iter().enumerate().min_by_key(|(_, end)| turn(end)).expect("Ends aren't empty.");
+            let next = ends.swap_remove(index);
+
+            if next == start {
+                break;
+            }
+            direction = Some(next - current);
+            points.push(next);
+            current = next;
+        }
+
+        // drop corners that lie on a straight line
+        let count = points.len();
+        loops.push((0..count)
+            .filter(|index| {
+                let previous = points[(index + count - 1) % count];
+                let next = points[(index + 1) % count];
+                let point = points[*index];
+                !(previous.x == point.x && point.x == next.x || previous.y == point.y && point.y == next.y)
+            })
+            .map(|index| points[index])
+            .collect());
+    }
+
+    loops
+}
+
+fn rgba(color: [u8; 3]) -> Rgba<u8> {
+    Rgba([color[0], color[1], color[2], 255])
+}
+
+fn hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{Permutation, Placement, ShapeBag, ShapePermutation};
+
+    fn pos(x: i32, y: i32) -> GlobalPos {
+        GlobalPos { x, y }
+    }
+
+    /// Twice the area inside the loop, positive going clockwise with y down.
+    fn signed_area(corners: &[GlobalPos]) -> i32 {
+        (0..corners.len())
+            .map(|index| {
+                let (a, b) = (corners[index], corners[(index + 1) % corners.len()]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum()
+    }
+
+    #[test]
+    fn ring_has_an_outside_and_a_hole_going_opposite_ways() {
+        let ring: Vec<GlobalPos> = (0..3)
+            .flat_map(|y| (0..3).map(move |x| pos(x, y)))
+            .filter(|tile| *tile != pos(1, 1))
+            .collect();
+
+        let mut loops = outlines(&ring);
+        loops.sort_by_key(|corners| -signed_area(corners).abs());
+
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].len(), 4);
+        assert_eq!(loops[1].len(), 4);
+        assert_eq!(signed_area(&loops[0]), 18);
+        assert_eq!(signed_area(&loops[1]), -2);
+    }
+
+    #[test]
+    fn tiles_touching_at_a_corner_get_a_loop_each() {
+        let loops = outlines(&[pos(0, 0), pos(1, 1)]);
+
+        assert_eq!(loops.len(), 2);
+        for corners in &loops {
+            assert_eq!(corners.len(), 4);
+            assert_eq!(signed_area(corners), 2);
+        }
+    }
+
+    #[test]
+    fn image_and_svg_cover_the_drawn_bounds() {
+        let mut solver = Solver::new(ShapeBag::parse("XX\n", 2).unwrap(), 0);
+        for pos in [pos(0, 0), pos(1, 1)] {
+            solver.place(&Placement {
+                permutation: ShapePermutation { index: 0, permutation: Permutation::from_index(0) },
+                pos,
+            });
+        }
+
+        let options = RenderOptions { cell_size: 5, ..RenderOptions::default() };
+        let bounds = solver.drawn_bounds();
+        assert_eq!((bounds.width(), bounds.height()), (3, 2));
+
+        let image = solver.to_image(&options);
+        assert_eq!(image.dimensions(), (15, 10));
+
+        let svg = solver.to_svg(&options);
+        assert!(svg.contains(r#"width="15" height="10" viewBox="0 0 15 10""#), "{}", svg);
+        assert_eq!(svg.matches("<path").count(), 2);
+    }
+}
@@ -0,0 +1,133 @@
+//! Random restarts: solve the same bag again with different seeds and keep
+//! the smallest board.
+
+use std::{str::FromStr, time::Duration};
+
+use super::Solver;
+
+/// When to stop restarting.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RestartLimit {
+    Runs(usize),
+    Time(Duration),
+}
+
+/// Parses a run count like `20`, or a time budget in seconds like `30s`.
+impl FromStr for RestartLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.strip_suffix('s') {
+            Some(seconds) => seconds.trim().parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.)
+                .map(|seconds| RestartLimit::Time(Duration::from_secs_f64(seconds)))
+                .ok_or_else(|| format!("invalid time budget {}, expected seconds like 30s", s)),
+            None => s.parse::<usize>()
+                .map(RestartLimit::Runs)
+                .map_err(|_| format!("invalid restart count {}, expected runs like 20 or seconds like 30s", s)),
+        }
+    }
+}
+
+impl RestartLimit {
+    /// Whether to start another run after `runs` runs taking `elapsed` so far.
+    /// There is always at least one run.
+    pub fn allows(&self, runs: usize, elapsed: Duration) -> bool {
+        match self {
+            RestartLimit::Runs(limit) => runs < (*limit).max(1),
+            RestartLimit::Time(budget) => runs == 0 || elapsed < *budget,
+        }
+    }
+}
+
+/// Runs complete solves from solvers built by `build`, seeded `seed`, `seed + 1`, ...
+pub struct RandomRestarts<F: FnMut(u64) -> Solver> {
+    build: F,
+    seed: u64,
+    pub runs: usize,
+    /// The finished solver with the fewest shapes left over, smallest area first.
+    pub best: Option<Solver>,
+}
+
+impl<F: FnMut(u64) -> Solver> RandomRestarts<F> {
+    pub fn new(seed: u64, build: F) -> RandomRestarts<F> {
+        RandomRestarts {
+            build,
+            seed,
+            runs: 0,
+            best: None,
+        }
+    }
+
+    pub fn next_seed(&self) -> u64 {
+        self.seed.wrapping_add(self.runs as u64)
+    }
+
+    /// Solves once more. Returns the area of the run and whether it became the best.
+    pub fn run(&mut self) -> (i32, bool) {
+        let seed = self.next_seed();
+        let mut solver = (self.build)(seed);
+        solver.solve();
+        self.runs += 1;
+
+        let rank = |solver: &Solver| (solver.bag.remaining_total(), solver.board.bounds.area());
+
+        let area = solver.board.bounds.area();
+        let improved = self.best.as_ref().is_none_or(|best| rank(&solver) < rank(best));
+        if improved {
+            self.best = Some(solver);
+        }
+
+        (area, improved)
+    }
+
+    pub fn best_area(&self) -> Option<i32> {
+        self.best.as_ref().map(|best| best.board.bounds.area())
+    }
+
+    /// Builds an empty solver with the seed of the best run, to replay it on.
+    pub fn build_best(&mut self) -> Option<Solver> {
+        let seed = self.best.as_ref()?.board.seed;
+        Some((self.build)(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::ShapeBag;
+
+    #[test]
+    fn limit_parses_runs_and_seconds() {
+        assert_eq!("3".parse::<RestartLimit>(), Ok(RestartLimit::Runs(3)));
+        assert_eq!(" 2s ".parse::<RestartLimit>(), Ok(RestartLimit::Time(Duration::from_secs(2))));
+        assert_eq!("0.5s".parse::<RestartLimit>(), Ok(RestartLimit::Time(Duration::from_millis(500))));
+    }
+
+    #[test]
+    fn limit_rejects_garbage() {
+        for text in ["", "lots", "-1", "3x", "s", "-2s", "infs"] {
+            assert!(text.parse::<RestartLimit>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn limit_always_allows_one_run() {
+        assert!(RestartLimit::Runs(0).allows(0, Duration::ZERO));
+        assert!(!RestartLimit::Runs(0).allows(1, Duration::ZERO));
+        assert!(RestartLimit::Time(Duration::ZERO).allows(0, Duration::from_secs(1)));
+        assert!(!RestartLimit::Time(Duration::ZERO).allows(1, Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn restarts_keep_the_smallest_run() {
+        let mut restarts = RandomRestarts::new(20, |seed| Solver::new(ShapeBag::load(1), 0).with_seed(seed));
+
+        let areas: Vec<i32> = (0..4).map(|_| restarts.run().0).collect();
+        assert_eq!(restarts.runs, 4);
+        assert_eq!(restarts.best_area(), areas.iter().min().copied());
+        assert_eq!(restarts.build_best().unwrap().board.seed, restarts.best.as_ref().unwrap().board.seed);
+    }
+}
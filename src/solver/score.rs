@@ -0,0 +1,257 @@
+//! Placement scoring as a weighted sum of named terms. Every term is oriented
+//! so that bigger is better; a weight of 0 switches the term off.
+
+use std::{fmt, str::FromStr};
+
+use super::{iter_moore, BoardState, GlobalPos, ShapeMask};
+
+/// Raw, unweighted term values for one candidate placement.
+#[derive(Clone, Default, Debug)]
+pub struct ScoreTerms {
+    /// Taken neighbors of the border tiles the shape covers.
+    pub adjacency: i32,
+    /// Closeness of the covered border tiles to the placed tiles' center of mass.
+    pub distance: i32,
+    /// Negated growth of the bounding box area.
+    pub area: i32,
+    /// Negated number of empty cells in pockets the shape walls in.
+    pub holes: i32,
+    /// Negated growth of the outline of all placed tiles.
+    pub perimeter: i32,
+    /// Negated number of empty cells inside the grown bounding box.
+    pub emptiness: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoreWeights {
+    pub adjacency: i32,
+    pub distance: i32,
+    pub area: i32,
+    pub holes: i32,
+    pub perimeter: i32,
+    pub emptiness: i32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights {
+            adjacency: 1,
+            distance: 1,
+            area: 1,
+            holes: 0,
+            perimeter: 0,
+            emptiness: 0,
+        }
+    }
+}
+
+impl ScoreWeights {
+    pub const NAMES: [&'static str; 6] = [
+        "adjacency",
+        "distance",
+        "area",
+        "holes",
+        "perimeter",
+        "emptiness",
+    ];
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut i32> {
+        match name {
+            "adjacency" => Some(&mut self.adjacency),
+            "distance" => Some(&mut self.distance),
+            "area" => Some(&mut self.area),
+            "holes" => Some(&mut self.holes),
+            "perimeter" => Some(&mut self.perimeter),
+            "emptiness" => Some(&mut self.emptiness),
+            _ => None,
+        }
+    }
+
+    /// Sets one weight from a `name=value` pair.
+    pub fn set(&mut self, assignment: &str) -> Result<(), String> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=value, got {}", assignment))?;
+
+        let value = value.trim().parse::<i32>()
+            .map_err(|_| format!("invalid weight for {}: {}", name.trim(), value.trim()))?;
+
+        let weight = self.get_mut(name.trim())
+            .ok_or_else(|| format!("unknown score term {}, expected one of {}", name.trim(), Self::NAMES.join(", ")))?;
+
+        *weight = value;
+        Ok(())
+    }
+
+    pub fn apply(&self, terms: &ScoreTerms) -> i32 {
+        self.adjacency * terms.adjacency
+            + self.distance * terms.distance
+            + self.area * terms.area
+            + self.holes * terms.holes
+            + self.perimeter * terms.perimeter
+            + self.emptiness * terms.emptiness
+    }
+}
+
+/// Parses a comma or whitespace separated list of `name=value` pairs on top of the defaults.
+impl FromStr for ScoreWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = ScoreWeights::default();
+        for assignment in s.split(|c: char| c == ',' || c.is_whitespace()).filter(|x| !x.is_empty()) {
+            weights.set(assignment)?;
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for ScoreWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "adjacency={},distance={},area={},holes={},perimeter={},emptiness={}",
+            self.adjacency, self.distance, self.area, self.holes, self.perimeter, self.emptiness,
+        )
+    }
+}
+
+impl BoardState {
+    /// Measures every term the weights care about, or `None` if the shape doesn't fit
+    /// or walls in a pocket smaller than `min_pocket` cells. Only pockets up to
+    /// `max_pocket` cells count as holes, bigger ones can still take a piece or two.
+    pub fn get_placement_terms(
+        &self,
+        offset: &GlobalPos,
+        mask: &ShapeMask,
+        min_pocket: usize,
+        max_pocket: usize,
+    ) -> Option<ScoreTerms> {
+
+        if !self.fits(mask, *offset) {
+            return None;
+        }
+
+        let mut terms = ScoreTerms::default();
+        for tile_pos in &mask.tiles {
+            if let Some(border) = self.border.get(&(*tile_pos + *offset)) {
+                terms.adjacency += border.adjacency_score;
+                terms.distance += border.distance_score;
+            }
+        }
+
+        let mut expanded_bounds = self.bounds.clone();
+        expanded_bounds.expand(&(mask.min + *offset));
+        expanded_bounds.expand(&(mask.max + *offset));
+
+        // subtract score for total bounds size?
+        terms.area = self.bounds.area() - expanded_bounds.area();
+
+        let weights = &self.weights;
+        if min_pocket < 2 && weights.holes == 0 && weights.perimeter == 0 && weights.emptiness == 0 {
+            return Some(terms);
+        }
+
+        // a handful of tiles, a vec beats hashing here
+        let placed: Vec<GlobalPos> = mask.tiles.iter().map(|pos| *pos + *offset).collect();
+
+        if min_pocket > 1 || weights.holes != 0 {
+            // only look as far as needed to find pockets that are too small, unless we count holes
+            let limit = if weights.holes != 0 {
+                max_pocket.max(min_pocket.saturating_sub(1))
+            } else {
+                min_pocket - 1
+            };
+
+            for pocket in self.pockets_around(&placed, self.pocket_bounds(&expanded_bounds), limit) {
+                if pocket.len() < min_pocket {
+                    return None;
+                }
+                terms.holes -= pocket.len() as i32;
+            }
+        }
+
+        if weights.perimeter != 0 {
+            for global_pos in &placed {
+                for neighbor in iter_moore(*global_pos) {
+                    if placed.contains(&neighbor) {
+                        continue;
+                    }
+                    if self.is_blocked(&neighbor) {
+                        terms.perimeter += 1;
+                    } else {
+                        terms.perimeter -= 1;
+                    }
+                }
+            }
+        }
+
+        // sum empty tiles in bounds...
+        if weights.emptiness != 0 {
+            for x in expanded_bounds.min_x..expanded_bounds.max_x + 1 {
+                for y in expanded_bounds.min_y..expanded_bounds.max_y + 1 {
+                    let pos = GlobalPos { x, y };
+                    if !placed.contains(&pos) && !self.is_blocked(&pos) {
+                        terms.emptiness -= 1;
+                    }
+                }
+            }
+        }
+
+        Some(terms)
+    }
+
+    pub fn get_placement_score(
+        &self,
+        offset: &GlobalPos,
+        mask: &ShapeMask,
+        min_pocket: usize,
+        max_pocket: usize,
+    ) -> Option<i32> {
+        self.get_placement_terms(offset, mask, min_pocket, max_pocket)
+            .map(|terms| self.weights.apply(&terms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_parse_onto_the_named_terms() {
+        let weights: ScoreWeights = "holes=3, perimeter=-2 area=0".parse().unwrap();
+
+        assert_eq!(weights, ScoreWeights {
+            adjacency: 1,
+            distance: 1,
+            area: 0,
+            holes: 3,
+            perimeter: -2,
+            emptiness: 0,
+        });
+        assert_eq!(weights.to_string().parse::<ScoreWeights>().unwrap(), weights);
+        assert_eq!("".parse::<ScoreWeights>().unwrap(), ScoreWeights::default());
+    }
+
+    #[test]
+    fn weights_apply_to_their_own_terms() {
+        let weights: ScoreWeights = "adjacency=0,distance=0,area=2,emptiness=-1".parse().unwrap();
+        let terms = ScoreTerms {
+            adjacency: 100,
+            distance: 100,
+            area: -3,
+            holes: -7,
+            perimeter: -5,
+            emptiness: -4,
+        };
+
+        assert_eq!(weights.apply(&terms), 2 * -3 + 4);
+    }
+
+    #[test]
+    fn weights_reject_unknown_terms_and_bad_values() {
+        assert!("height=1".parse::<ScoreWeights>().unwrap_err().starts_with("unknown score term height"));
+        assert!("holes=lots".parse::<ScoreWeights>().unwrap_err().starts_with("invalid weight for holes"));
+        assert!("holes".parse::<ScoreWeights>().unwrap_err().starts_with("expected name=value"));
+    }
+}
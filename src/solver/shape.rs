@@ -0,0 +1,435 @@
+use std::path::Path;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{bitboard::ShapeMask, pos::GlobalPos};
+
+#[derive(Copy, Clone)]
+pub struct ShapePermutation {
+    pub index: usize,
+    pub permutation: Permutation,
+}
+
+pub const PERMUTATIONS: u8 = 8;
+
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Permutation {
+    pub rotation: u8,
+    pub flipped: bool,
+}
+
+impl Permutation {
+    pub fn from_index(index: u8) -> Permutation {
+        Permutation { 
+            rotation: index % 4,
+            flipped: index >= 4,
+        }
+    }
+
+    /// Inverse of `from_index`.
+    pub fn index(&self) -> usize {
+        (self.rotation % 4) as usize + if self.flipped { 4 } else { 0 }
+    }
+}
+
+#[derive(Clone)]
+pub struct Shape {
+    pub index: usize,
+    pub bounds: (i32, i32),
+    pub tiles: Vec<GlobalPos>,
+    /// One permutation per distinct orientation, symmetric duplicates left out.
+    pub orientations: Vec<Permutation>,
+    /// Tiles and row masks for every permutation, by `Permutation::index`.
+    masks: Vec<ShapeMask>,
+}
+
+impl Shape {
+    pub fn from_string(
+        index: usize,
+        string: &str,
+    ) -> Shape {
+
+        let mut bounds_x = 0;
+        let mut tiles = Vec::new();
+
+        let mut y = 0;
+        for line in string.lines() {
+            let mut x = 0;
+            for char in line.chars() {
+                if !char.is_whitespace() && char != '.' {
+                    tiles.push(GlobalPos { x, y });
+                }
+                x += 1;
+            }
+            y += 1;
+            bounds_x = bounds_x.max(x);
+        }
+
+        let mut shape = Shape {
+            index,
+            tiles,
+            bounds: (bounds_x, y),
+            orientations: Vec::new(),
+            masks: Vec::new(),
+        };
+        shape.orientations = shape.distinct_orientations();
+        shape.masks = (0..PERMUTATIONS)
+            .map(|index| ShapeMask::new(shape.iter_pos(&Permutation::from_index(index))))
+            .collect();
+        shape
+    }
+
+    pub fn iter_pos(&self, permutation: &Permutation) -> Vec<GlobalPos> {
+        let mut ret = self.tiles.clone();
+
+        let rotations = permutation.rotation % 4;
+        if rotations != 0 {
+            let rotation_matrix = match rotations {
+                1 => [[0, -1], [1, 0]],   // 90 degrees counterclockwise
+                2 => [[-1, 0], [0, -1]],  // 180 degrees
+                3 => [[0, 1], [-1, 0]],  // 270 degrees counterclockwise
+                _ => [[1, 0], [0, 1]],   // 0 degrees (identity matrix)
+            };
+
+            for point in &mut ret {
+                let x = point.x * rotation_matrix[0][0] + point.y * rotation_matrix[0][1];
+                let y = point.x * rotation_matrix[1][0] + point.y * rotation_matrix[1][1];
+                point.x = x;
+                point.y = y;
+            }
+        }
+
+        if permutation.flipped {
+            for point in &mut ret {
+                point.x = -point.x;
+                //point.y = y;
+            }
+        }
+
+        ret
+    }
+
+    pub fn mask(&self, permutation: &Permutation) -> &ShapeMask {
+        &self.masks[permutation.index()]
+    }
+
+    /// Permutations whose tiles differ once moved to the origin.
+    fn distinct_orientations(&self) -> Vec<Permutation> {
+        let mut seen: Vec<Vec<GlobalPos>> = Vec::new();
+        let mut orientations = Vec::new();
+
+        for permutation_index in 0..PERMUTATIONS {
+            let permutation = Permutation::from_index(permutation_index);
+            let tiles = self.iter_pos(&permutation);
+
+            let min = GlobalPos {
+                x: tiles.iter().map(|pos| pos.x).min().unwrap_or(0),
+                y: tiles.iter().map(|pos| pos.y).min().unwrap_or(0),
+            };
+
+            let mut normalized: Vec<GlobalPos> = tiles.iter().map(|pos| *pos - min).collect();
+            normalized.sort();
+
+            if !seen.contains(&normalized) {
+                seen.push(normalized);
+                orientations.push(permutation);
+            }
+        }
+
+        orientations
+    }
+}
+
+pub const DEFAULT_PIECES: &str = include_str!("../../assets/pieces/default.txt");
+
+#[derive(Clone)]
+pub struct ShapeBag {
+    remaining: Vec<u16>,
+    /// Quantities fixed by the piece file, `None` means use the global count.
+    counts: Vec<Option<u16>>,
+    vec: Vec<Shape>,
+}
+
+impl ShapeBag {
+
+    pub fn iter_available(&self) -> impl Iterator<Item=&Shape> {
+        self.vec.iter().filter(|shape| self.remaining[shape.index] > 0)
+    }
+
+    pub fn shape_count(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn remaining(&self, shape_index: usize) -> u16 {
+        self.remaining[shape_index]
+    }
+
+    /// Number of shapes left to place, counting every copy.
+    pub fn remaining_total(&self) -> usize {
+        self.remaining.iter().map(|count| *count as usize).sum()
+    }
+
+    /// Tile count of the smallest shape still in the bag.
+    pub fn min_available_size(&self) -> Option<usize> {
+        self.iter_available().map(|shape| shape.tiles.len()).min()
+    }
+
+    /// Tile count of the smallest shape left once one `shape_index` is taken out.
+    pub fn min_size_without(&self, shape_index: usize) -> Option<usize> {
+        self.sizes_without(shape_index).min()
+    }
+
+    /// Tile count of the largest shape left once one `shape_index` is taken out.
+    pub fn max_size_without(&self, shape_index: usize) -> Option<usize> {
+        self.sizes_without(shape_index).max()
+    }
+
+    fn sizes_without(&self, shape_index: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vec
+            .iter()
+            .filter(move |shape| {
+                let taken = if shape.index == shape_index { 1 } else { 0 };
+                self.remaining[shape.index] > taken
+            })
+            .map(|shape| shape.tiles.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining.iter().all(|count| *count == 0)
+    }
+
+    pub fn try_pop(&mut self, shape_index: usize) -> bool {
+
+        if self.remaining[shape_index] > 0 {
+            self.remaining[shape_index] -= 1;
+            return true;
+        }
+        false
+    }
+
+    /// Returns a shape taken with `try_pop`.
+    pub fn put_back(&mut self, shape_index: usize) {
+        self.remaining[shape_index] += 1;
+    }
+
+    /// Fixes the quantity of one shape, `reset` keeps it from then on.
+    pub fn set_count(&mut self, shape_index: usize, count: u16) {
+        self.counts[shape_index] = Some(count);
+        self.remaining[shape_index] = count;
+    }
+
+    /// Fixes the quantities given as `Some`, in shape order.
+    pub fn set_counts(&mut self, counts: &[Option<u16>]) -> Result<(), String> {
+        if counts.len() > self.vec.len() {
+            return Err(format!("Got {} counts for {} pieces", counts.len(), self.vec.len()));
+        }
+
+        for (shape_index, count) in counts.iter().enumerate() {
+            if let Some(count) = count {
+                self.set_count(shape_index, *count);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses comma separated per-shape counts like `3,,1`, where a blank entry
+    /// leaves that shape at the global count.
+    pub fn parse_counts(text: &str) -> Result<Vec<Option<u16>>, String> {
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        text.split(',')
+            .map(|entry| {
+                let entry = entry.trim();
+                if entry.is_empty() {
+                    return Ok(None);
+                }
+                entry.parse::<u16>()
+                    .map(Some)
+                    .map_err(|_| format!("Invalid count {}", entry))
+            })
+            .collect()
+    }
+
+    pub fn reset(&mut self, count: u16) {
+        for (remaining, fixed) in self.remaining.iter_mut().zip(&self.counts) {
+            *remaining = fixed.unwrap_or(count);
+        }
+    }
+
+    pub fn shape(&self, shape_index: usize) -> &Shape {
+        &self.vec[shape_index]
+    }
+
+    pub fn iter_pos(&self, shape_permutation: &ShapePermutation) -> Vec<GlobalPos> {
+        self.vec[shape_permutation.index].iter_pos(&shape_permutation.permutation)
+    }
+
+    pub fn mask(&self, shape_permutation: &ShapePermutation) -> &ShapeMask {
+        self.vec[shape_permutation.index].mask(&shape_permutation.permutation)
+    }
+
+    pub fn get_random_permutation(&self, rng: &mut impl Rng) -> Option<ShapePermutation> {
+        let available: Vec<usize> = self.iter_available().map(|shape| shape.index).collect();
+        if available.is_empty() {
+            return None;
+        }
+
+        let index = available[rng.gen::<usize>() % available.len()];
+        let flipped = rng.gen::<i32>() % 2 == 0;
+        let rotation = rng.gen::<u8>() % 4;
+
+        Some(ShapePermutation {
+            index,
+            permutation: Permutation {
+                flipped,
+                rotation,
+            },
+        })
+    }
+
+    pub fn load(count: u16) -> ShapeBag {
+        ShapeBag::parse(DEFAULT_PIECES, count).expect("Built-in pieces failed to parse.")
+    }
+
+    pub fn from_file(path: impl AsRef<Path>, count: u16) -> Result<ShapeBag, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        ShapeBag::parse(&text, count)
+    }
+
+    /// Parses pieces drawn as character grids, separated by blank lines.
+    /// Lines starting with `//` are comments, and a piece may start with
+    /// `count: N` to override `count` for that piece.
+    pub fn parse(text: &str, count: u16) -> Result<ShapeBag, String> {
+        let mut shapes = Vec::new();
+        let mut counts = Vec::new();
+
+        let mut block = String::new();
+        let mut block_count = None;
+
+        let lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim_start().starts_with("//"))
+            .chain(std::iter::once((0, "")));
+
+        for (line_index, line) in lines {
+            if line.trim().is_empty() {
+                if !block.is_empty() {
+                    let size = block.lines().map(|line| line.chars().count()).max().unwrap_or(0)
+                        .max(block.lines().count());
+                    if size as i32 > ShapeMask::MAX_WIDTH {
+                        return Err(format!("Piece {} is larger than {} tiles", shapes.len(), ShapeMask::MAX_WIDTH));
+                    }
+
+                    let shape = Shape::from_string(shapes.len(), &block);
+                    if shape.tiles.is_empty() {
+                        return Err(format!("Piece {} has no tiles", shapes.len()));
+                    }
+                    shapes.push(shape);
+                    counts.push(block_count.take());
+                    block.clear();
+                }
+                continue;
+            }
+
+            if let Some(value) = line.trim().strip_prefix("count:") {
+                if !block.is_empty() || block_count.is_some() {
+                    return Err(format!("Line {}: count must come before the piece", line_index + 1));
+                }
+                block_count = Some(value.trim().parse::<u16>()
+                    .map_err(|_| format!("Line {}: invalid count {}", line_index + 1, value.trim()))?);
+                continue;
+            }
+
+            block.push_str(line);
+            block.push('\n');
+        }
+
+        if block_count.is_some() {
+            return Err("Count without a piece at the end of the file".into());
+        }
+
+        if shapes.is_empty() {
+            return Err("No pieces found".into());
+        }
+
+        let remaining = counts.iter().map(|fixed| fixed.unwrap_or(count)).collect();
+
+        Ok(ShapeBag {
+            remaining,
+            counts,
+            vec: shapes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PENTOMINOES: &str = include_str!("../../assets/pieces/pentominoes.txt");
+    const TETROMINOES: &str = include_str!("../../assets/pieces/tetrominoes.txt");
+
+    fn orientation_count(text: &str) -> usize {
+        let bag = ShapeBag::parse(text, 1).unwrap();
+        bag.vec.iter().map(|shape| shape.orientations.len()).sum()
+    }
+
+    #[test]
+    fn free_pentominoes_have_63_fixed_orientations() {
+        assert_eq!(ShapeBag::parse(PENTOMINOES, 1).unwrap().shape_count(), 12);
+        assert_eq!(orientation_count(PENTOMINOES), 63);
+    }
+
+    #[test]
+    fn free_tetrominoes_have_19_fixed_orientations() {
+        assert_eq!(orientation_count(TETROMINOES), 19);
+    }
+
+    #[test]
+    fn parse_skips_comments_and_reads_counts() {
+        let bag = ShapeBag::parse("// two pieces\n\nXX\n\ncount: 3\nX.\nXX\n  // trailing\n", 2).unwrap();
+
+        assert_eq!(bag.shape_count(), 2);
+        assert_eq!(bag.remaining(0), 2);
+        assert_eq!(bag.remaining(1), 3);
+        assert_eq!(bag.vec[1].tiles, vec![
+            GlobalPos { x: 0, y: 0 },
+            GlobalPos { x: 0, y: 1 },
+            GlobalPos { x: 1, y: 1 },
+        ]);
+        assert_eq!(bag.vec[1].bounds, (2, 2));
+    }
+
+    #[test]
+    fn reset_keeps_counts_from_the_file() {
+        let mut bag = ShapeBag::parse("X\n\ncount: 1\nXX\n", 4).unwrap();
+        bag.try_pop(1);
+        bag.reset(5);
+
+        assert_eq!(bag.remaining(0), 5);
+        assert_eq!(bag.remaining(1), 1);
+    }
+
+    #[test]
+    fn parse_rejects_bad_files() {
+        assert!(ShapeBag::parse("// nothing\n", 1).is_err());
+        assert!(ShapeBag::parse("XX\ncount: 2\n", 1).is_err());
+        assert!(ShapeBag::parse("XX\n\ncount: 2\n", 1).is_err());
+        assert!(ShapeBag::parse("count: many\nX\n", 1).is_err());
+        assert!(ShapeBag::parse("...\n", 1).is_err());
+    }
+
+    #[test]
+    fn parse_counts_leaves_blanks_at_the_global_count() {
+        assert_eq!(ShapeBag::parse_counts("3,,1").unwrap(), vec![Some(3), None, Some(1)]);
+        assert_eq!(ShapeBag::parse_counts(" ").unwrap(), Vec::<Option<u16>>::new());
+        assert!(ShapeBag::parse_counts("3,x").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    beam::BeamStrategy,
+    exact::ExactStrategy,
+    find_candidates, BoardState, Placement, ShapeBag, INITIAL,
+};
+
+/// Decides where the next shape goes. The solver places whatever is returned,
+/// so a strategy only has to read the board and bag.
+pub trait PlacementStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Picks the next shape, permutation and position, or `None` if nothing fits.
+    fn next_placement(&mut self, board: &mut BoardState, bag: &ShapeBag) -> Option<Placement>;
+
+    /// Extra `key=value` fields for the run summary, if the strategy has any.
+    fn report(&self) -> Option<String> {
+        None
+    }
+
+    /// Called when the board is cleared, drop anything planned for the old one.
+    fn reset(&mut self) {}
+}
+
+/// Commits to the single highest scoring placement every step.
+pub struct GreedyStrategy;
+
+impl PlacementStrategy for GreedyStrategy {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn next_placement(&mut self, board: &mut BoardState, bag: &ShapeBag) -> Option<Placement> {
+
+        if board.is_empty() {
+
+            // degenerate case: just place any ole tile first.
+            return bag.get_random_permutation(&mut board.rng).map(|permutation| Placement {
+                permutation,
+                pos: INITIAL,
+            });
+        }
+
+        // Take best attempt...
+        let mut best_attempts = find_candidates(board, bag);
+        best_attempts.sort_by_key(|x| x.score);
+        best_attempts.pop().map(|candidate| candidate.placement)
+    }
+}
+
+/// Knobs for the strategies that have any.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrategySettings {
+    pub beam_width: usize,
+    /// Largest rectangle the exact strategy tries before giving up.
+    pub max_area: i32,
+}
+
+impl Default for StrategySettings {
+    fn default() -> Self {
+        StrategySettings {
+            beam_width: 1,
+            max_area: 1000,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StrategyKind {
+    Greedy,
+    Beam,
+    Exact,
+}
+
+impl StrategyKind {
+    pub const ALL: [StrategyKind; 3] = [
+        StrategyKind::Greedy,
+        StrategyKind::Beam,
+        StrategyKind::Exact,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrategyKind::Greedy => "greedy",
+            StrategyKind::Beam => "beam",
+            StrategyKind::Exact => "exact",
+        }
+    }
+
+    pub fn build(&self, settings: &StrategySettings) -> Box<dyn PlacementStrategy> {
+        match self {
+            StrategyKind::Greedy => Box::new(GreedyStrategy),
+            StrategyKind::Beam => Box::new(BeamStrategy::new(settings.beam_width)),
+            StrategyKind::Exact => Box::new(ExactStrategy::new(settings.max_area)),
+        }
+    }
+}
+
+impl FromStr for StrategyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StrategyKind::ALL
+            .iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = StrategyKind::ALL.iter().map(|kind| kind.name()).collect();
+                format!("unknown strategy {}, expected one of {}", s, names.join(", "))
+            })
+    }
+}